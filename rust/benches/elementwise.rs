@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use neural_network::{ActivationFunction, Matrix, Sigmoid, Tanh};
+use std::time::Duration;
+
+fn random(size: usize) -> Matrix {
+    let mut m = Matrix::new(size, size);
+    m.randomize();
+    m
+}
+
+// Closure-based `map`, which is what activations and arithmetic went through
+// before the vectorized kernels.
+fn elementwise(c: &mut Criterion) {
+    let mut group = c.benchmark_group("elementwise");
+    group.sample_size(20).warm_up_time(Duration::from_secs(1));
+
+    let size = 512;
+    let (a, b) = (random(size), random(size));
+    group.throughput(criterion::Throughput::Elements((size * size) as u64));
+
+    group.bench_function(BenchmarkId::new("add", "map"), |bench| {
+        bench.iter(|| {
+            let mut out = a.clone();
+            for (x, y) in out.data.iter_mut().zip(&black_box(&b).data) {
+                *x += y;
+            }
+            out
+        })
+    });
+    group.bench_function(BenchmarkId::new("add", "simd"), |bench| {
+        bench.iter(|| {
+            let mut out = a.clone();
+            out.add_assign(black_box(&b)).unwrap();
+            out
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("sigmoid", "map"), |bench| {
+        bench.iter(|| black_box(&a).map(|x| ActivationFunction::<f64>::activate(&Sigmoid, x)))
+    });
+    group.bench_function(BenchmarkId::new("sigmoid", "simd"), |bench| {
+        bench.iter(|| {
+            let mut out = a.clone();
+            out.apply_chunks(|chunk| Sigmoid.activate_in_place(chunk));
+            out
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("tanh", "map"), |bench| {
+        bench.iter(|| black_box(&a).map(|x| ActivationFunction::<f64>::activate(&Tanh, x)))
+    });
+    group.bench_function(BenchmarkId::new("tanh", "simd"), |bench| {
+        bench.iter(|| {
+            let mut out = a.clone();
+            out.apply_chunks(|chunk| Tanh.activate_in_place(chunk));
+            out
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, elementwise);
+criterion_main!(benches);
@@ -0,0 +1,57 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use neural_network::Matrix;
+use rayon::prelude::*;
+use std::time::Duration;
+
+// The previous `Matrix::dot`: a row-parallel triple loop that walks `b` down its
+// columns and clones both inputs up front.
+fn naive_dot(a: &Matrix, b: &Matrix) -> Matrix {
+    let a_data = a.data.clone();
+    let b_data = b.data.clone();
+    let (a_cols, b_cols) = (a.cols, b.cols);
+
+    let data = (0..a.rows)
+        .into_par_iter()
+        .flat_map(|i| {
+            let mut row = vec![0.0; b_cols];
+            for (j, value) in row.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for k in 0..a_cols {
+                    sum += a_data[i * a_cols + k] * b_data[k * b_cols + j];
+                }
+                *value = sum;
+            }
+            row
+        })
+        .collect();
+
+    Matrix { rows: a.rows, cols: b.cols, data }
+}
+
+fn random(size: usize) -> Matrix {
+    let mut m = Matrix::new(size, size);
+    m.randomize();
+    m
+}
+
+fn matmul(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matmul");
+    group.sample_size(10).warm_up_time(Duration::from_secs(1));
+
+    for size in [512, 2048] {
+        let (a, b) = (random(size), random(size));
+        group.throughput(criterion::Throughput::Elements((2 * size * size * size) as u64));
+
+        group.bench_with_input(BenchmarkId::new("naive", size), &size, |bench, _| {
+            bench.iter(|| naive_dot(black_box(&a), black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("blocked", size), &size, |bench, _| {
+            bench.iter(|| Matrix::dot(black_box(&a), black_box(&b)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, matmul);
+criterion_main!(benches);
@@ -1,8 +1,8 @@
-use neural_network::{NeuralNetwork, ReLU, Softmax};
+use neural_network::metrics::class_of;
+use neural_network::{Average, Metric, NeuralNetwork, ReLU, Softmax};
 use std::sync::Arc;
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
-use rand::distributions::Standard;
 
 fn main() {
     println!("\n=== Multi-Class Classification (3 Classes) ===");
@@ -44,37 +44,27 @@ fn main() {
     nn.fit(&inputs, &targets, 300, true).unwrap();
     
     println!("\nMulti-Class Classification Test Results:");
-    let mut correct = 0;
-    let num_test_points = 15;
-    
-    for _ in 0..num_test_points {
-        let idx = rng.gen_range(0..total_samples);
-        let input = &inputs[idx];
-        let output = nn.predict(input).unwrap();
-        
-        let mut predicted_class = 0;
-        for j in 1..num_classes {
-            if output[j] > output[predicted_class] {
-                predicted_class = j;
-            }
-        }
-        
-        let mut actual_class = 0;
-        for j in 1..num_classes {
-            if targets[idx][j] > targets[idx][actual_class] {
-                actual_class = j;
-            }
-        }
+    let mut test_inputs = Vec::new();
+    let mut test_targets = Vec::new();
+    for _ in 0..30 {
+        let c = rng.gen_range(0..num_classes);
+        let x = centers[c][0] + rng.gen::<f64>() * 0.1 - 0.05;
+        let y = centers[c][1] + rng.gen::<f64>() * 0.1 - 0.05;
         
-        if predicted_class == actual_class {
-            correct += 1;
-        }
+        let mut target = vec![0.0; num_classes];
+        target[c] = 1.0;
         
+        test_inputs.push(vec![x, y]);
+        test_targets.push(target);
+    }
+    
+    for (input, target) in test_inputs.iter().zip(&test_targets).take(5) {
+        let output = nn.predict(input).unwrap();
         println!("Point ({:.2}, {:.2}): Predicted Class {}, Actual Class {}",
-            input[0], input[1], predicted_class, actual_class);
+            input[0], input[1], class_of(&output), class_of(target));
     }
     
-    let accuracy = (correct as f64 / num_test_points as f64) * 100.0;
-    println!("Test Accuracy: {:.2}% ({}/{} correct)", 
-             accuracy, correct, num_test_points);
-}
\ No newline at end of file
+    nn.set_metrics(vec![Metric::Accuracy, Metric::F1(Average::Macro)]);
+    let report = nn.evaluate(&test_inputs, &test_targets).unwrap();
+    println!("Test set: {}", report);
+}
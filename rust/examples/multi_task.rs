@@ -0,0 +1,49 @@
+use neural_network::{BinaryCrossEntropy, Graph, Layer, Linear, MeanSquaredError, Metric, Sigmoid, Tanh};
+use std::sync::Arc;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+fn main() {
+    println!("\n=== Multi-Task Learning (Classification + Regression) ===");
+
+    let mut rng = ChaCha8Rng::seed_from_u64(42);
+
+    let num_samples = 200;
+    let mut inputs = Vec::with_capacity(num_samples);
+    let mut targets = Vec::with_capacity(num_samples);
+
+    for _ in 0..num_samples {
+        let x = rng.gen::<f64>();
+        let y = rng.gen::<f64>();
+
+        let inside_circle = if (x - 0.5).powi(2) + (y - 0.5).powi(2) < 0.1 { 1.0 } else { 0.0 };
+        let distance = ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt();
+
+        inputs.push(vec![vec![x, y]]);
+        targets.push(vec![vec![inside_circle], vec![distance]]);
+    }
+
+    let mut model = Graph::new(0.02);
+    let features = model.add_input(2);
+    let shared = model.add_node(Layer::new(2, 16, Arc::new(Tanh)), features).unwrap();
+    let shared = model.add_node(Layer::new(16, 8, Arc::new(Tanh)), shared).unwrap();
+    let class_head = model.add_node(Layer::new(8, 1, Arc::new(Sigmoid)), shared).unwrap();
+    let distance_head = model.add_node(Layer::new(8, 1, Arc::new(Linear)), shared).unwrap();
+
+    model.add_head("inside", class_head, Arc::new(BinaryCrossEntropy), 1.0).unwrap();
+    model.add_head("distance", distance_head, Arc::new(MeanSquaredError), 2.0).unwrap();
+    model.set_head_metrics("inside", vec![Metric::Accuracy]).unwrap();
+    model.set_head_metrics("distance", vec![Metric::MeanAbsoluteError]).unwrap();
+
+    println!("Training multi-task network...");
+    model.fit(&inputs, &targets, 500, true).unwrap();
+
+    println!("\nSample predictions:");
+    println!("x\ty\tInside (pred/actual)\tDistance (pred/actual)");
+    for i in 0..5 {
+        let input = &inputs[i][0];
+        let outputs = model.predict(&[input]).unwrap();
+        println!("{:.2}\t{:.2}\t{:.2} / {:.0}\t\t{:.3} / {:.3}",
+            input[0], input[1], outputs[0][0], targets[i][0][0], outputs[1][0], targets[i][1][0]);
+    }
+}
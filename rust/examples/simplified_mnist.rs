@@ -58,17 +58,17 @@ fn main() {
     let mut inputs = Vec::with_capacity(num_samples);
     let mut targets = Vec::with_capacity(num_samples);
     
-    for digit in 0..num_digits {
+    for (digit, pattern) in digit_patterns.iter().enumerate() {
         for variation in 0..5 {
             let mut input = Vec::with_capacity(pixels_per_digit);
             
-            for i in 0..8 {
-                for j in 0..8 {
+            for row in pattern {
+                for &pixel in row {
                     let noise = rng.gen::<f64>();
                     if noise < 0.1 && variation > 0 {
-                        input.push(1.0 - digit_patterns[digit][i][j] as f64);
+                        input.push(1.0 - pixel as f64);
                     } else {
-                        input.push(digit_patterns[digit][i][j] as f64);
+                        input.push(pixel as f64);
                     }
                 }
             }
@@ -93,11 +93,11 @@ fn main() {
     
     println!("\nSimplified MNIST Test Results:");
     
-    for digit in 0..num_digits {
+    for (digit, pattern) in digit_patterns.iter().enumerate() {
         let mut input = Vec::with_capacity(pixels_per_digit);
-        for i in 0..8 {
-            for j in 0..8 {
-                input.push(digit_patterns[digit][i][j] as f64);
+        for row in pattern {
+            for &pixel in row {
+                input.push(pixel as f64);
             }
         }
         
@@ -114,15 +114,15 @@ fn main() {
             digit, predicted_digit, output[predicted_digit] * 100.0);
         
         print!("Class probabilities: ");
-        for d in 0..num_digits {
-            print!("[{}]: {:.2}% ", d, output[d] * 100.0);
+        for (d, probability) in output.iter().enumerate() {
+            print!("[{}]: {:.2}% ", d, probability * 100.0);
         }
         println!();
         
         println!("Pattern:");
-        for i in 0..8 {
-            for j in 0..8 {
-                print!("{}", if digit_patterns[digit][i][j] == 1 { "█" } else { " " });
+        for row in pattern {
+            for &pixel in row {
+                print!("{}", if pixel == 1 { "█" } else { " " });
             }
             println!();
         }
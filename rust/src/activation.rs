@@ -1,69 +1,113 @@
-pub trait ActivationFunction: Send + Sync {
-    fn activate(&self, x: f64) -> f64;
-    fn derivative(&self, y: f64) -> f64;
+use crate::float::Float;
+use crate::simd::{self, Unary};
+
+// Generic over the element type; the built-in activations work for any `Float`.
+pub trait ActivationFunction<T: Float = f64>: Send + Sync {
+    fn activate(&self, x: T) -> T;
+    fn derivative(&self, y: T) -> T;
     
-    fn activate_vec(&self, input: &[f64]) -> Vec<f64> {
+    fn activate_vec(&self, input: &[T]) -> Vec<T> {
         input.iter().map(|&x| self.activate(x)).collect()
     }
     
-    fn derivative_vec(&self, output: &[f64]) -> Vec<f64> {
+    fn derivative_vec(&self, output: &[T]) -> Vec<T> {
         output.iter().map(|&y| self.derivative(y)).collect()
     }
+    
+    // Element-wise `activate` over a slice, overridden where a vectorized
+    // kernel exists.
+    fn activate_in_place(&self, values: &mut [T]) {
+        for value in values {
+            *value = self.activate(*value);
+        }
+    }
 }
 
 pub struct Sigmoid;
 
-impl ActivationFunction for Sigmoid {
-    fn activate(&self, x: f64) -> f64 {
-        1.0 / (1.0 + (-x).exp())
+impl<T: Float> ActivationFunction<T> for Sigmoid {
+    fn activate(&self, x: T) -> T {
+        T::ONE / (T::ONE + (-x).exp())
     }
 
-    fn derivative(&self, y: f64) -> f64 {
-        y * (1.0 - y)
+    fn derivative(&self, y: T) -> T {
+        y * (T::ONE - y)
+    }
+    
+    fn activate_in_place(&self, values: &mut [T]) {
+        simd::unary(Unary::Sigmoid, values);
     }
 }
 
 pub struct ReLU;
 
-impl ActivationFunction for ReLU {
-    fn activate(&self, x: f64) -> f64 {
-        x.max(0.0)
+impl<T: Float> ActivationFunction<T> for ReLU {
+    fn activate(&self, x: T) -> T {
+        x.max(T::ZERO)
     }
 
-    fn derivative(&self, y: f64) -> f64 {
-        if y > 0.0 { 1.0 } else { 0.0 }
+    fn derivative(&self, y: T) -> T {
+        if y > T::ZERO { T::ONE } else { T::ZERO }
+    }
+}
+
+pub struct Linear;
+
+impl<T: Float> ActivationFunction<T> for Linear {
+    fn activate(&self, x: T) -> T {
+        x
+    }
+
+    fn derivative(&self, _y: T) -> T {
+        T::ONE
+    }
+    
+    fn activate_in_place(&self, _values: &mut [T]) {}
+}
+
+pub struct Tanh;
+
+impl<T: Float> ActivationFunction<T> for Tanh {
+    fn activate(&self, x: T) -> T {
+        x.tanh()
+    }
+
+    fn derivative(&self, y: T) -> T {
+        T::ONE - y * y
+    }
+    
+    fn activate_in_place(&self, values: &mut [T]) {
+        simd::unary(Unary::Tanh, values);
     }
 }
 
 pub struct Softmax;
 
-impl ActivationFunction for Softmax {
-    fn activate(&self, x: f64) -> f64 {
-        1.0 / (1.0 + (-x).exp())
+impl<T: Float> ActivationFunction<T> for Softmax {
+    fn activate(&self, x: T) -> T {
+        T::ONE / (T::ONE + (-x).exp())
     }
 
-    fn derivative(&self, y: f64) -> f64 {
-        y * (1.0 - y)
+    fn derivative(&self, y: T) -> T {
+        y * (T::ONE - y)
     }
     
-    fn activate_vec(&self, input: &[f64]) -> Vec<f64> {
+    fn activate_vec(&self, input: &[T]) -> Vec<T> {
         if input.is_empty() {
             return Vec::new();
         }
         
-        let max_val = input.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        let max_val = input.iter().fold(T::NEG_INFINITY, |a, &b| a.max(b));
         
-        let mut output: Vec<f64> = input.iter()
+        let mut output: Vec<T> = input.iter()
             .map(|&x| (x - max_val).exp())
             .collect();
         
-        let sum: f64 = output.iter().sum();
+        let sum: T = output.iter().copied().sum();
         
-        if sum < 1e-10 {
-            let uniform_prob = 1.0 / input.len() as f64;
-            for val in &mut output {
-                *val = uniform_prob;
-            }
+        if sum < T::from_f64(1e-10) {
+            let uniform_prob = T::ONE / T::from_f64(input.len() as f64);
+            output.fill(uniform_prob);
         } else {
             for val in &mut output {
                 *val /= sum;
@@ -77,6 +121,7 @@ impl ActivationFunction for Softmax {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gradcheck;
 
     #[test]
     fn test_sigmoid() {
@@ -95,6 +140,21 @@ mod tests {
         assert_eq!(relu.derivative(0.0), 0.0);
     }
 
+    #[test]
+    fn test_linear() {
+        let linear = Linear;
+        assert_eq!(linear.activate(-3.5), -3.5);
+        assert_eq!(linear.derivative(42.0), 1.0);
+    }
+
+    #[test]
+    fn test_tanh() {
+        let tanh = Tanh;
+        assert_eq!(tanh.activate(0.0), 0.0);
+        assert!((tanh.activate(1.0) - 0.7615941559557649).abs() < 1e-10);
+        assert!((tanh.derivative(0.5) - 0.75).abs() < 1e-10);
+    }
+
     #[test]
     fn test_softmax() {
         let softmax = Softmax;
@@ -107,4 +167,14 @@ mod tests {
         assert!(output[0] > output[1]);
         assert!(output[1] > output[2]);
     }
+
+    #[test]
+    fn test_derivatives_match_finite_difference() {
+        // Kinks and the saturated tails are avoided
+        let points = [-2.5, -0.7, -0.1, 0.3, 1.2, 3.0];
+        assert!(gradcheck::check_activation(&Sigmoid, &points).passed(1e-6));
+        assert!(gradcheck::check_activation(&Tanh, &points).passed(1e-6));
+        assert!(gradcheck::check_activation(&Linear, &points).passed(1e-6));
+        assert!(gradcheck::check_activation(&ReLU, &points).passed(1e-6));
+    }
 }
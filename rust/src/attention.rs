@@ -0,0 +1,457 @@
+use crate::matrix::Matrix;
+use crate::normalization::LayerNorm;
+
+// Sequences are laid out as `d_model x time`, one column per token.
+
+fn row_block(m: &Matrix, start: usize, count: usize) -> Matrix {
+    Matrix {
+        rows: count,
+        cols: m.cols,
+        data: m.data[start * m.cols..(start + count) * m.cols].to_vec(),
+    }
+}
+
+fn set_row_block(m: &mut Matrix, start: usize, block: &Matrix) {
+    m.data[start * m.cols..(start + block.rows) * m.cols].copy_from_slice(&block.data);
+}
+
+fn update(weights: &mut Matrix, gradient: &Matrix, learning_rate: f64) {
+    for (w, g) in weights.data.iter_mut().zip(&gradient.data) {
+        *w += learning_rate * g;
+    }
+}
+
+fn random_matrix(rows: usize, cols: usize, scale: f64) -> Matrix {
+    let mut m = Matrix::new(rows, cols);
+    m.randomize();
+    m.multiply(scale)
+}
+
+// Mask entries are 1.0 where query `i` (row) may attend to key `j` (column)
+// and 0.0 where it may not.
+pub fn causal_mask(len: usize) -> Matrix {
+    let mut mask = Matrix::new(len, len);
+    for i in 0..len {
+        for j in 0..=i {
+            mask.set(i, j, 1.0);
+        }
+    }
+    mask
+}
+
+pub fn positional_encoding(d_model: usize, len: usize) -> Matrix {
+    let mut encoding = Matrix::new(d_model, len);
+    for pos in 0..len {
+        for i in 0..d_model {
+            let angle = pos as f64 / 10000f64.powf((2 * (i / 2)) as f64 / d_model as f64);
+            encoding.set(i, pos, if i % 2 == 0 { angle.sin() } else { angle.cos() });
+        }
+    }
+    encoding
+}
+
+// `q` and `k` are `d_k x len`, `v` is `d_v x len_k`. Returns the `d_v x len_q`
+// output together with the `len_q x len_k` attention weights.
+pub fn scaled_dot_product_attention(
+    q: &Matrix,
+    k: &Matrix,
+    v: &Matrix,
+    mask: Option<&Matrix>,
+) -> Result<(Matrix, Matrix), &'static str> {
+    if q.rows != k.rows || k.cols != v.cols {
+        return Err("Incompatible matrix sizes for attention");
+    }
+    if let Some(mask) = mask {
+        if mask.rows != q.cols || mask.cols != k.cols {
+            return Err("Attention mask size mismatch");
+        }
+    }
+
+    let scale = 1.0 / (q.rows as f64).sqrt();
+    let mut weights = Matrix::dot_tn(q, k)?.multiply(scale);
+
+    for i in 0..weights.rows {
+        let row = &mut weights.data[i * weights.cols..(i + 1) * weights.cols];
+        if let Some(mask) = mask {
+            for (j, value) in row.iter_mut().enumerate() {
+                if mask.get(i, j) == 0.0 {
+                    *value = f64::NEG_INFINITY;
+                }
+            }
+        }
+
+        let max_val = row.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        if max_val == f64::NEG_INFINITY {
+            // A fully masked query attends to nothing
+            row.fill(0.0);
+            continue;
+        }
+        for value in row.iter_mut() {
+            *value = (*value - max_val).exp();
+        }
+        let sum: f64 = row.iter().sum();
+        for value in row.iter_mut() {
+            *value /= sum;
+        }
+    }
+
+    let output = Matrix::dot_nt(v, &weights)?;
+    Ok((output, weights))
+}
+
+// Returns the errors for (q, k, v) given the error on the attention output.
+pub fn scaled_dot_product_attention_backward(
+    q: &Matrix,
+    k: &Matrix,
+    v: &Matrix,
+    weights: &Matrix,
+    output_error: &Matrix,
+) -> Result<(Matrix, Matrix, Matrix), &'static str> {
+    let scale = 1.0 / (q.rows as f64).sqrt();
+
+    let v_error = Matrix::dot(output_error, weights)?;
+    let weights_error = Matrix::dot_tn(output_error, v)?;
+
+    // Softmax backward, row by row: dS = P * (dP - sum(dP * P))
+    let mut score_error = Matrix::new(weights.rows, weights.cols);
+    for i in 0..weights.rows {
+        let range = i * weights.cols..(i + 1) * weights.cols;
+        let p = &weights.data[range.clone()];
+        let dp = &weights_error.data[range.clone()];
+        let dot: f64 = p.iter().zip(dp).map(|(a, b)| a * b).sum();
+        for ((s, &p), &dp) in score_error.data[range].iter_mut().zip(p).zip(dp) {
+            *s = p * (dp - dot) * scale;
+        }
+    }
+
+    let q_error = Matrix::dot_nt(k, &score_error)?;
+    let k_error = Matrix::dot(q, &score_error)?;
+    Ok((q_error, k_error, v_error))
+}
+
+struct AttentionCache {
+    input: Matrix,
+    q: Matrix,
+    k: Matrix,
+    v: Matrix,
+    concat: Matrix,
+    head_weights: Vec<Matrix>,
+}
+
+pub struct MultiHeadAttention {
+    pub d_model: usize,
+    pub num_heads: usize,
+    pub query_weights: Matrix,
+    pub key_weights: Matrix,
+    pub value_weights: Matrix,
+    pub output_weights: Matrix,
+    pub mask: Option<Matrix>,
+    cache: Option<AttentionCache>,
+}
+
+impl MultiHeadAttention {
+    pub fn new(d_model: usize, num_heads: usize) -> Result<Self, &'static str> {
+        if num_heads == 0 || !d_model.is_multiple_of(num_heads) {
+            return Err("d_model must be divisible by the number of heads");
+        }
+
+        let scale = 1.0 / (d_model as f64).sqrt();
+        Ok(MultiHeadAttention {
+            d_model,
+            num_heads,
+            query_weights: random_matrix(d_model, d_model, scale),
+            key_weights: random_matrix(d_model, d_model, scale),
+            value_weights: random_matrix(d_model, d_model, scale),
+            output_weights: random_matrix(d_model, d_model, scale),
+            mask: None,
+            cache: None,
+        })
+    }
+
+    pub fn with_mask(mut self, mask: Matrix) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    pub fn head_size(&self) -> usize {
+        self.d_model / self.num_heads
+    }
+
+    // Attention weights of each head from the most recent forward pass.
+    pub fn attention_weights(&self) -> Option<&[Matrix]> {
+        self.cache.as_ref().map(|c| c.head_weights.as_slice())
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.rows != self.d_model {
+            return Err("Input size does not match attention layer");
+        }
+
+        let q = Matrix::dot(&self.query_weights, input)?;
+        let k = Matrix::dot(&self.key_weights, input)?;
+        let v = Matrix::dot(&self.value_weights, input)?;
+
+        let head_size = self.head_size();
+        let mut concat = Matrix::new(self.d_model, input.cols);
+        let mut head_weights = Vec::with_capacity(self.num_heads);
+        for h in 0..self.num_heads {
+            let start = h * head_size;
+            let (head_output, weights) = scaled_dot_product_attention(
+                &row_block(&q, start, head_size),
+                &row_block(&k, start, head_size),
+                &row_block(&v, start, head_size),
+                self.mask.as_ref(),
+            )?;
+            set_row_block(&mut concat, start, &head_output);
+            head_weights.push(weights);
+        }
+
+        let output = Matrix::dot(&self.output_weights, &concat)?;
+        self.cache = Some(AttentionCache {
+            input: input.clone(),
+            q,
+            k,
+            v,
+            concat,
+            head_weights,
+        });
+        Ok(output)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.query_weights, &mut self.key_weights, &mut self.value_weights, &mut self.output_weights]
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let cache = self.cache.as_ref().ok_or("No input stored for backpropagation")?;
+
+        let output_gradient = Matrix::dot_nt(output_error, &cache.concat)?;
+        let concat_error = Matrix::dot_tn(&self.output_weights, output_error)?;
+
+        let head_size = self.head_size();
+        let mut q_error = Matrix::new(cache.q.rows, cache.q.cols);
+        let mut k_error = Matrix::new(cache.k.rows, cache.k.cols);
+        let mut v_error = Matrix::new(cache.v.rows, cache.v.cols);
+        for (h, weights) in cache.head_weights.iter().enumerate() {
+            let start = h * head_size;
+            let (dq, dk, dv) = scaled_dot_product_attention_backward(
+                &row_block(&cache.q, start, head_size),
+                &row_block(&cache.k, start, head_size),
+                &row_block(&cache.v, start, head_size),
+                weights,
+                &row_block(&concat_error, start, head_size),
+            )?;
+            set_row_block(&mut q_error, start, &dq);
+            set_row_block(&mut k_error, start, &dk);
+            set_row_block(&mut v_error, start, &dv);
+        }
+
+        let query_gradient = Matrix::dot_nt(&q_error, &cache.input)?;
+        let key_gradient = Matrix::dot_nt(&k_error, &cache.input)?;
+        let value_gradient = Matrix::dot_nt(&v_error, &cache.input)?;
+
+        let input_error = Matrix::dot_tn(&self.query_weights, &q_error)?
+            .add(&Matrix::dot_tn(&self.key_weights, &k_error)?)?
+            .add(&Matrix::dot_tn(&self.value_weights, &v_error)?)?;
+
+        update(&mut self.query_weights, &query_gradient, learning_rate);
+        update(&mut self.key_weights, &key_gradient, learning_rate);
+        update(&mut self.value_weights, &value_gradient, learning_rate);
+        update(&mut self.output_weights, &output_gradient, learning_rate);
+
+        Ok(input_error)
+    }
+}
+
+// Two dense projections with a ReLU in between, applied to every token.
+struct PositionwiseFeedForward {
+    w1: Matrix,
+    b1: Matrix,
+    w2: Matrix,
+    b2: Matrix,
+    last_input: Option<Matrix>,
+    last_hidden: Option<Matrix>,
+}
+
+impl PositionwiseFeedForward {
+    fn new(d_model: usize, d_ff: usize) -> Self {
+        PositionwiseFeedForward {
+            w1: random_matrix(d_ff, d_model, 1.0 / (d_model as f64).sqrt()),
+            b1: Matrix::new(d_ff, 1),
+            w2: random_matrix(d_model, d_ff, 1.0 / (d_ff as f64).sqrt()),
+            b2: Matrix::new(d_model, 1),
+            last_input: None,
+            last_hidden: None,
+        }
+    }
+
+    fn add_bias(m: &mut Matrix, bias: &Matrix) {
+        for i in 0..m.rows {
+            for value in &mut m.data[i * m.cols..(i + 1) * m.cols] {
+                *value += bias.data[i];
+            }
+        }
+    }
+
+    fn sum_columns(m: &Matrix) -> Matrix {
+        let sums: Vec<f64> = (0..m.rows)
+            .map(|i| m.data[i * m.cols..(i + 1) * m.cols].iter().sum())
+            .collect();
+        Matrix::from_array(&sums)
+    }
+
+    fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        let mut hidden = Matrix::dot(&self.w1, input)?;
+        Self::add_bias(&mut hidden, &self.b1);
+        hidden.apply_in_place(|x| x.max(0.0));
+
+        let mut output = Matrix::dot(&self.w2, &hidden)?;
+        Self::add_bias(&mut output, &self.b2);
+
+        self.last_input = Some(input.clone());
+        self.last_hidden = Some(hidden);
+        Ok(output)
+    }
+
+    fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let input = self.last_input.as_ref().ok_or("No input stored for backpropagation")?;
+        let hidden = self.last_hidden.as_ref().ok_or("No activation stored for backpropagation")?;
+
+        let w2_gradient = Matrix::dot_nt(output_error, hidden)?;
+        let b2_gradient = Self::sum_columns(output_error);
+
+        let relu_derivative = hidden.map(|h| if h > 0.0 { 1.0 } else { 0.0 });
+        let hidden_error = Matrix::hadamard(
+            &Matrix::dot_tn(&self.w2, output_error)?,
+            &relu_derivative,
+        )?;
+        let w1_gradient = Matrix::dot_nt(&hidden_error, input)?;
+        let b1_gradient = Self::sum_columns(&hidden_error);
+        let input_error = Matrix::dot_tn(&self.w1, &hidden_error)?;
+
+        update(&mut self.w1, &w1_gradient, learning_rate);
+        update(&mut self.b1, &b1_gradient, learning_rate);
+        update(&mut self.w2, &w2_gradient, learning_rate);
+        update(&mut self.b2, &b2_gradient, learning_rate);
+
+        Ok(input_error)
+    }
+}
+
+// Post-norm encoder block: x = LN(x + MHA(x)); x = LN(x + FFN(x)).
+pub struct TransformerEncoderBlock {
+    pub attention: MultiHeadAttention,
+    pub attention_norm: LayerNorm,
+    pub feed_forward_norm: LayerNorm,
+    feed_forward: PositionwiseFeedForward,
+}
+
+impl TransformerEncoderBlock {
+    pub fn new(d_model: usize, num_heads: usize, d_ff: usize) -> Result<Self, &'static str> {
+        Ok(TransformerEncoderBlock {
+            attention: MultiHeadAttention::new(d_model, num_heads)?,
+            attention_norm: LayerNorm::new(d_model),
+            feed_forward_norm: LayerNorm::new(d_model),
+            feed_forward: PositionwiseFeedForward::new(d_model, d_ff),
+        })
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        let attended = self.attention.feed_forward(input)?;
+        let normed = self.attention_norm.feed_forward(&input.add(&attended)?)?;
+        let transformed = self.feed_forward.feed_forward(&normed)?;
+        self.feed_forward_norm.feed_forward(&normed.add(&transformed)?)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        let mut parameters = self.attention.parameters_mut();
+        parameters.extend(self.attention_norm.parameters_mut());
+        parameters.extend(vec![&mut self.feed_forward.w1, &mut self.feed_forward.b1, &mut self.feed_forward.w2, &mut self.feed_forward.b2]);
+        parameters.extend(self.feed_forward_norm.parameters_mut());
+        parameters
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let residual_error = self.feed_forward_norm.backpropagate(output_error, learning_rate)?;
+        let normed_error = residual_error.add(&self.feed_forward.backpropagate(&residual_error, learning_rate)?)?;
+        let residual_error = self.attention_norm.backpropagate(&normed_error, learning_rate)?;
+        residual_error.add(&self.attention.backpropagate(&residual_error, learning_rate)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck;
+
+    fn tokens(d_model: usize, len: usize) -> Matrix {
+        let data = (0..d_model * len).map(|i| ((i * 37 % 11) as f64 - 5.0) * 0.15).collect();
+        Matrix { rows: d_model, cols: len, data }
+    }
+
+    #[test]
+    fn test_attention_weights_sum_to_one_and_respect_mask() {
+        let q = tokens(2, 3);
+        let k = tokens(2, 3).multiply(0.5);
+        let v = tokens(4, 3);
+        let mask = causal_mask(3);
+        let (output, weights) = scaled_dot_product_attention(&q, &k, &v, Some(&mask)).unwrap();
+
+        assert_eq!((output.rows, output.cols), (4, 3));
+        for i in 0..3 {
+            let sum: f64 = (0..3).map(|j| weights.get(i, j)).sum();
+            assert!((sum - 1.0).abs() < 1e-12);
+            for j in (i + 1)..3 {
+                assert_eq!(weights.get(i, j), 0.0);
+            }
+        }
+        // The first query can only see the first value
+        for r in 0..4 {
+            assert!((output.get(r, 0) - v.get(r, 0)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_multi_head_attention_gradients() {
+        let mut attention = MultiHeadAttention::new(4, 2).unwrap().with_mask(causal_mask(3));
+        let input = tokens(4, 3);
+
+        assert!(gradcheck::check_module(&mut attention, &input).unwrap().passed(1e-6));
+        let parameters: [fn(&mut MultiHeadAttention) -> &mut Matrix; 4] = [
+            |a| &mut a.query_weights,
+            |a| &mut a.key_weights,
+            |a| &mut a.value_weights,
+            |a| &mut a.output_weights,
+        ];
+        for parameter in parameters {
+            let report = gradcheck::check_module_parameters(&mut attention, &input, parameter).unwrap();
+            assert!(report.passed(1e-5), "{:?}", report);
+        }
+    }
+
+    #[test]
+    fn test_encoder_block_gradients() {
+        let mut block = TransformerEncoderBlock::new(4, 2, 8).unwrap();
+        let input = tokens(4, 3).add(&positional_encoding(4, 3)).unwrap();
+
+        let output = block.feed_forward(&input).unwrap();
+        assert_eq!((output.rows, output.cols), (4, 3));
+        let report = gradcheck::check_module(&mut block, &input).unwrap();
+        assert!(report.passed(1e-5), "{:?}", report);
+    }
+
+    #[test]
+    fn test_positional_encoding() {
+        let encoding = positional_encoding(4, 3);
+        assert_eq!((encoding.rows, encoding.cols), (4, 3));
+        assert_eq!(encoding.get(0, 0), 0.0);
+        assert_eq!(encoding.get(1, 0), 1.0);
+        assert!((encoding.get(0, 1) - 1f64.sin()).abs() < 1e-12);
+        assert!((encoding.get(3, 2) - (2.0 / 100.0f64).cos()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rejects_indivisible_heads() {
+        assert!(MultiHeadAttention::new(6, 4).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::matrix::Matrix;
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "neural-network-checkpoint 1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckpointPolicy {
+    // Write `epoch-NNNN.ckpt` after every N completed epochs.
+    EveryEpochs(usize),
+    // Overwrite `best.ckpt` whenever the validation loss reaches a new low.
+    // Needs validation data set on the network.
+    BestValidation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpointing {
+    pub directory: PathBuf,
+    pub policy: CheckpointPolicy,
+}
+
+impl Checkpointing {
+    pub fn new<P: Into<PathBuf>>(directory: P, policy: CheckpointPolicy) -> Self {
+        Checkpointing { directory: directory.into(), policy }
+    }
+}
+
+// Everything `fit` needs to carry on exactly where it stopped. Training is plain
+// SGD with a fixed learning rate, so there are no optimizer moments or scheduler
+// counters to store beyond the learning rate itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    // Number of completed epochs.
+    pub epoch: usize,
+    pub learning_rate: f64,
+    // (weights, biases) per layer.
+    pub layers: Vec<(Matrix, Matrix)>,
+    // Shuffling RNG, positioned just after the last completed epoch.
+    pub rng: ChaCha8Rng,
+    pub best_validation_loss: Option<f64>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Floats are stored as their bit patterns so a reload is bit-for-bit exact.
+fn write_f64(out: &mut String, value: f64) {
+    out.push_str(&format!("{:016x}", value.to_bits()));
+}
+
+fn parse_f64(token: Option<&str>) -> io::Result<f64> {
+    let token = token.ok_or_else(|| invalid("Unexpected end of checkpoint"))?;
+    u64::from_str_radix(token, 16)
+        .map(f64::from_bits)
+        .map_err(|_| invalid("Malformed float in checkpoint"))
+}
+
+fn parse_usize(token: Option<&str>) -> io::Result<usize> {
+    token.ok_or_else(|| invalid("Unexpected end of checkpoint"))?
+        .parse()
+        .map_err(|_| invalid("Malformed integer in checkpoint"))
+}
+
+impl Checkpoint {
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{}\nepoch {}\nlearning_rate ", HEADER, self.epoch);
+        write_f64(&mut out, self.learning_rate);
+
+        out.push_str("\nrng ");
+        for byte in self.rng.get_seed() {
+            out.push_str(&format!("{:02x}", byte));
+        }
+        out.push_str(&format!(" {} {}\n", self.rng.get_stream(), self.rng.get_word_pos()));
+
+        out.push_str("best_validation_loss ");
+        match self.best_validation_loss {
+            Some(loss) => write_f64(&mut out, loss),
+            None => out.push_str("none"),
+        }
+
+        out.push_str(&format!("\nlayers {}\n", self.layers.len()));
+        for (weights, biases) in &self.layers {
+            for matrix in [weights, biases] {
+                out.push_str(&format!("{} {}", matrix.rows, matrix.cols));
+                for &value in &matrix.data {
+                    out.push(' ');
+                    write_f64(&mut out, value);
+                }
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    pub fn from_text(text: &str) -> io::Result<Checkpoint> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("Not a checkpoint file"));
+        }
+
+        let mut field = |name: &str| -> io::Result<Vec<&str>> {
+            let line = lines.next().ok_or_else(|| invalid("Unexpected end of checkpoint"))?;
+            let mut tokens = line.split_whitespace();
+            if tokens.next() != Some(name) {
+                return Err(invalid("Unexpected field in checkpoint"));
+            }
+            Ok(tokens.collect())
+        };
+
+        let epoch = parse_usize(field("epoch")?.first().copied())?;
+        let learning_rate = parse_f64(field("learning_rate")?.first().copied())?;
+
+        let rng_fields = field("rng")?;
+        let seed_hex = rng_fields.first().copied().unwrap_or("");
+        if seed_hex.len() != 64 || !seed_hex.is_ascii() {
+            return Err(invalid("Malformed RNG seed in checkpoint"));
+        }
+        let mut seed = [0u8; 32];
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&seed_hex[2 * i..2 * i + 2], 16)
+                .map_err(|_| invalid("Malformed RNG seed in checkpoint"))?;
+        }
+        let stream: u64 = rng_fields.get(1).and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("Malformed RNG stream in checkpoint"))?;
+        let word_pos: u128 = rng_fields.get(2).and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("Malformed RNG position in checkpoint"))?;
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(stream);
+        rng.set_word_pos(word_pos);
+
+        let best_validation_loss = match field("best_validation_loss")?.first().copied() {
+            Some("none") => None,
+            token => Some(parse_f64(token)?),
+        };
+
+        let layer_count = parse_usize(field("layers")?.first().copied())?;
+        let mut read_matrix = || -> io::Result<Matrix> {
+            let line = lines.next().ok_or_else(|| invalid("Unexpected end of checkpoint"))?;
+            let mut tokens = line.split_whitespace();
+            let rows = parse_usize(tokens.next())?;
+            let cols = parse_usize(tokens.next())?;
+            let data = tokens.map(|t| parse_f64(Some(t))).collect::<io::Result<Vec<f64>>>()?;
+            if rows.checked_mul(cols) != Some(data.len()) {
+                return Err(invalid("Matrix size mismatch in checkpoint"));
+            }
+            Ok(Matrix { rows, cols, data })
+        };
+
+        // The count comes from the file, so it isn't trusted to size anything
+        let mut layers = Vec::new();
+        for _ in 0..layer_count {
+            let weights = read_matrix()?;
+            let biases = read_matrix()?;
+            layers.push((weights, biases));
+        }
+
+        Ok(Checkpoint { epoch, learning_rate, layers, rng, best_validation_loss })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write then rename so a crash mid-write never leaves a truncated checkpoint
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, self.to_text())?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Checkpoint> {
+        Checkpoint::from_text(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_text_round_trip_is_exact() {
+        let mut rng = ChaCha8Rng::seed_from_u64(99);
+        rng.set_stream(3);
+        rng.next_u64();
+
+        let checkpoint = Checkpoint {
+            epoch: 12,
+            learning_rate: 0.1,
+            layers: vec![(
+                Matrix { rows: 2, cols: 1, data: vec![std::f64::consts::PI, -1e-300] },
+                Matrix { rows: 2, cols: 1, data: vec![0.1 + 0.2, f64::MAX] },
+            )],
+            rng,
+            best_validation_loss: Some(0.25),
+        };
+
+        let mut restored = Checkpoint::from_text(&checkpoint.to_text()).unwrap();
+        assert_eq!(restored, checkpoint);
+
+        let mut original_rng = checkpoint.rng.clone();
+        assert_eq!(restored.rng.next_u64(), original_rng.next_u64());
+    }
+
+    #[test]
+    fn test_rejects_malformed_text() {
+        assert!(Checkpoint::from_text("something else").is_err());
+        let truncated = format!("{}\nepoch 3\n", HEADER);
+        assert!(Checkpoint::from_text(&truncated).is_err());
+    }
+
+    // A valid checkpoint with one line replaced
+    fn with_line(index: usize, line: &str) -> String {
+        let checkpoint = Checkpoint {
+            epoch: 1,
+            learning_rate: 0.1,
+            layers: vec![(Matrix { rows: 1, cols: 1, data: vec![0.5] }, Matrix { rows: 1, cols: 1, data: vec![0.0] })],
+            rng: ChaCha8Rng::seed_from_u64(1),
+            best_validation_loss: None,
+        };
+        let text = checkpoint.to_text();
+        assert!(Checkpoint::from_text(&text).is_ok());
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines[index] = line;
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error_not_a_panic() {
+        let invalid = |text: &str| Checkpoint::from_text(text).unwrap_err().kind() == io::ErrorKind::InvalidData;
+
+        // 64 bytes, but with a multi-byte character straddling a hex pair
+        let seed = format!("{}é{}", "0".repeat(31), "0".repeat(31));
+        assert_eq!(seed.len(), 64);
+        assert!(invalid(&with_line(3, &format!("rng {} 0 0", seed))));
+
+        assert!(invalid(&with_line(5, &format!("layers {}", usize::MAX))));
+        assert!(invalid(&with_line(6, &format!("{} 2 1.0", usize::MAX))));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::float::Float;
+use crate::layer::Layer;
+use crate::matrix::Matrix;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientClipping {
+    // Clamps every gradient element to [-limit, limit].
+    Value(f64),
+    // Rescales each layer's gradients so their combined L2 norm is at most the limit.
+    Norm(f64),
+    // Rescales all gradients together so the L2 norm across every layer is at most the limit.
+    GlobalNorm(f64),
+}
+
+pub fn global_norm<T: Float>(layers: &[Vec<&mut Matrix<T>>]) -> f64 {
+    layers.iter().map(|layer| squared_norm(layer)).sum::<f64>().sqrt()
+}
+
+fn squared_norm<T: Float>(gradients: &[&mut Matrix<T>]) -> f64 {
+    gradients.iter()
+        .flat_map(|g| g.data.iter())
+        .map(|v| v.to_f64() * v.to_f64())
+        .sum()
+}
+
+// Unlike `Float::clamp` this can't panic on a bad limit, and NaN stays NaN for
+// the divergence checks to find.
+fn clamp<T: Float>(v: T, limit: T) -> T {
+    if v > limit {
+        limit
+    } else if v < -limit {
+        -limit
+    } else {
+        v
+    }
+}
+
+fn scale_to<T: Float>(gradients: &mut [&mut Matrix<T>], norm: f64, limit: f64) {
+    if norm > limit && norm > 0.0 {
+        let scale = T::from_f64(limit / norm);
+        for g in gradients.iter_mut() {
+            g.apply_in_place(|v| v * scale);
+        }
+    }
+}
+
+impl GradientClipping {
+    // Limits must be positive and finite; the setters reject anything else.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let limit = match *self {
+            GradientClipping::Value(limit) | GradientClipping::Norm(limit) | GradientClipping::GlobalNorm(limit) => limit,
+        };
+        if limit.is_finite() && limit > 0.0 {
+            Ok(())
+        } else {
+            Err("Gradient clipping limit must be positive and finite")
+        }
+    }
+
+    // `layers` holds each layer's gradient matrices, grouped per layer.
+    pub fn apply<T: Float>(&self, layers: &mut [Vec<&mut Matrix<T>>]) {
+        match *self {
+            GradientClipping::Value(limit) => {
+                let limit = T::from_f64(limit);
+                for g in layers.iter_mut().flat_map(|layer| layer.iter_mut()) {
+                    g.apply_in_place(|v| clamp(v, limit));
+                }
+            }
+            GradientClipping::Norm(limit) => {
+                for layer in layers.iter_mut() {
+                    let norm = squared_norm(layer).sqrt();
+                    scale_to(layer, norm, limit);
+                }
+            }
+            GradientClipping::GlobalNorm(limit) => {
+                let norm = global_norm(layers);
+                for layer in layers.iter_mut() {
+                    scale_to(layer, norm, limit);
+                }
+            }
+        }
+    }
+
+    // `apply` for the pending gradients of `layers`, read in place so that a
+    // training step doesn't allocate.
+    pub(crate) fn apply_to_layers<T: Float>(&self, layers: &mut [Layer<T>]) {
+        match *self {
+            GradientClipping::Value(limit) => {
+                let limit = T::from_f64(limit);
+                for (w, b) in layers.iter_mut().filter_map(Layer::gradients_mut) {
+                    w.apply_in_place(|v| clamp(v, limit));
+                    b.apply_in_place(|v| clamp(v, limit));
+                }
+            }
+            GradientClipping::Norm(limit) => {
+                for (w, b) in layers.iter_mut().filter_map(Layer::gradients_mut) {
+                    let mut gradients = [w, b];
+                    let norm = squared_norm(&gradients).sqrt();
+                    scale_to(&mut gradients, norm, limit);
+                }
+            }
+            GradientClipping::GlobalNorm(limit) => {
+                let norm = layers.iter_mut()
+                    .filter_map(Layer::gradients_mut)
+                    .map(|(w, b)| squared_norm(&[w, b]))
+                    .sum::<f64>()
+                    .sqrt();
+                for (w, b) in layers.iter_mut().filter_map(Layer::gradients_mut) {
+                    scale_to(&mut [w, b], norm, limit);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradients() -> (Matrix, Matrix, Matrix) {
+        (
+            Matrix::from_array(&[3.0, -4.0]),
+            Matrix::from_array(&[0.5]),
+            Matrix::from_array(&[0.0, 12.0]),
+        )
+    }
+
+    #[test]
+    fn test_clip_by_value() {
+        let (mut a, mut b, mut c) = gradients();
+        GradientClipping::Value(1.0).apply(&mut [vec![&mut a, &mut b], vec![&mut c]]);
+        assert_eq!(a.data, vec![1.0, -1.0]);
+        assert_eq!(b.data, vec![0.5]);
+        assert_eq!(c.data, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_bad_limits() {
+        assert!(GradientClipping::Value(-1.0).validate().is_err());
+        assert!(GradientClipping::Norm(f64::NAN).validate().is_err());
+        assert!(GradientClipping::GlobalNorm(0.0).validate().is_err());
+        assert!(GradientClipping::Norm(2.0).validate().is_ok());
+
+        // Set directly, a bad limit still doesn't panic, and NaN survives clipping
+        let mut a = Matrix::from_array(&[3.0, f64::NAN]);
+        GradientClipping::Value(-1.0).apply(&mut [vec![&mut a]]);
+        assert_eq!(a.data[0], -1.0);
+        assert!(a.data[1].is_nan());
+    }
+
+    #[test]
+    fn test_clip_by_layer_norm() {
+        let (mut a, mut b, mut c) = gradients();
+        GradientClipping::Norm(20.0).apply(&mut [vec![&mut a, &mut b], vec![&mut c]]);
+        assert_eq!(a.data, vec![3.0, -4.0]);
+
+        let (mut a, mut b, mut c) = gradients();
+        GradientClipping::Norm(6.0).apply(&mut [vec![&mut a], vec![&mut b, &mut c]]);
+        assert!((a.data[0] - 3.0).abs() < 1e-12);
+        let norm = (b.data[0].powi(2) + c.data[1].powi(2)).sqrt();
+        assert!((norm - 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_clip_by_global_norm() {
+        let (mut a, mut b, mut c) = gradients();
+        let mut layers = [vec![&mut a, &mut b], vec![&mut c]];
+        let before = global_norm(&layers);
+        assert!((before - (169.25f64).sqrt()).abs() < 1e-12);
+
+        GradientClipping::GlobalNorm(1.0).apply(&mut layers);
+        assert!((global_norm(&layers) - 1.0).abs() < 1e-12);
+        // Direction is preserved
+        assert!((a.data[0] / a.data[1] + 0.75).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::activation::ActivationFunction;
+use crate::matrix::Matrix;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    Valid,
+    // Left-pads so each output step only sees the current and earlier inputs,
+    // and the output keeps the input's length.
+    Causal,
+}
+
+// Sequences are laid out as `channels x time`, one row per channel.
+pub struct Conv1D {
+    pub in_channels: usize,
+    pub out_channels: usize,
+    pub kernel_size: usize,
+    pub dilation: usize,
+    pub padding: Padding,
+    pub weights: Matrix,
+    pub biases: Matrix,
+    activation: Arc<dyn ActivationFunction>,
+    last_input_len: Option<usize>,
+    last_columns: Option<Matrix>,
+    last_activation: Option<Matrix>,
+}
+
+impl Conv1D {
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel_size: usize,
+        dilation: usize,
+        padding: Padding,
+        activation: Arc<dyn ActivationFunction>,
+    ) -> Self {
+        let mut weights = Matrix::new(out_channels, in_channels * kernel_size);
+        let mut biases = Matrix::new(out_channels, 1);
+        weights.randomize();
+        biases.randomize();
+
+        Conv1D {
+            in_channels,
+            out_channels,
+            kernel_size,
+            dilation,
+            padding,
+            weights,
+            biases,
+            activation,
+            last_input_len: None,
+            last_columns: None,
+            last_activation: None,
+        }
+    }
+
+    fn receptive_field(&self) -> usize {
+        (self.kernel_size - 1) * self.dilation + 1
+    }
+
+    fn left_padding(&self) -> usize {
+        match self.padding {
+            Padding::Valid => 0,
+            Padding::Causal => self.receptive_field() - 1,
+        }
+    }
+
+    pub fn output_len(&self, input_len: usize) -> Result<usize, &'static str> {
+        if self.kernel_size == 0 || self.dilation == 0 {
+            return Err("Kernel size and dilation must be non-zero");
+        }
+        let padded = input_len + self.left_padding();
+        if padded < self.receptive_field() {
+            return Err("Sequence shorter than the kernel's receptive field");
+        }
+        Ok(padded - self.receptive_field() + 1)
+    }
+
+    // Unrolls the input so that every output step becomes one column of
+    // `in_channels * kernel_size` taps, turning the convolution into a single `dot`.
+    fn im2col(&self, input: &Matrix, out_len: usize) -> Matrix {
+        let pad = self.left_padding();
+        let mut columns = Matrix::new(self.in_channels * self.kernel_size, out_len);
+
+        for c in 0..self.in_channels {
+            for k in 0..self.kernel_size {
+                let row = c * self.kernel_size + k;
+                for t in 0..out_len {
+                    let src = t + k * self.dilation;
+                    if src >= pad {
+                        columns.data[row * out_len + t] = input.data[c * input.cols + src - pad];
+                    }
+                }
+            }
+        }
+
+        columns
+    }
+
+    fn col2im(&self, columns: &Matrix, input_len: usize) -> Matrix {
+        let pad = self.left_padding();
+        let out_len = columns.cols;
+        let mut input_error = Matrix::new(self.in_channels, input_len);
+
+        for c in 0..self.in_channels {
+            for k in 0..self.kernel_size {
+                let row = c * self.kernel_size + k;
+                for t in 0..out_len {
+                    let src = t + k * self.dilation;
+                    if src >= pad {
+                        input_error.data[c * input_len + src - pad] += columns.data[row * out_len + t];
+                    }
+                }
+            }
+        }
+
+        input_error
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.rows != self.in_channels {
+            return Err("Input channel count does not match layer");
+        }
+        let out_len = self.output_len(input.cols)?;
+
+        let columns = self.im2col(input, out_len);
+        let mut z = Matrix::dot(&self.weights, &columns)?;
+        for o in 0..self.out_channels {
+            let bias = self.biases.data[o];
+            for value in &mut z.data[o * out_len..(o + 1) * out_len] {
+                *value += bias;
+            }
+        }
+
+        let activation_output = z.map(|x| self.activation.activate(x));
+
+        self.last_input_len = Some(input.cols);
+        self.last_columns = Some(columns);
+        self.last_activation = Some(activation_output.clone());
+
+        Ok(activation_output)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let input_len = self.last_input_len.ok_or("No input stored for backpropagation")?;
+        let columns = self.last_columns.as_ref().ok_or("No input stored for backpropagation")?;
+        let last_activation = self.last_activation.as_ref().ok_or("No activation stored for backpropagation")?;
+
+        let activation_derivative = last_activation.map(|x| self.activation.derivative(x));
+        let delta = Matrix::hadamard(output_error, &activation_derivative)?;
+
+        let weight_gradient = Matrix::dot_nt(&delta, columns)?;
+        let column_error = Matrix::dot_tn(&self.weights, &delta)?;
+        let input_error = self.col2im(&column_error, input_len);
+
+        let weight_delta = weight_gradient.multiply(learning_rate);
+        for (w, d) in self.weights.data.iter_mut().zip(&weight_delta.data) {
+            *w += d;
+        }
+
+        for o in 0..self.out_channels {
+            let bias_gradient: f64 = delta.data[o * delta.cols..(o + 1) * delta.cols].iter().sum();
+            self.biases.data[o] += learning_rate * bias_gradient;
+        }
+
+        Ok(input_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::{Linear, ReLU, Sigmoid};
+    use crate::gradcheck;
+
+    fn sequence(channels: usize, values: &[f64]) -> Matrix {
+        Matrix {
+            rows: channels,
+            cols: values.len() / channels,
+            data: values.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_causal_dilated_convolution() {
+        let mut conv = Conv1D::new(1, 1, 2, 2, Padding::Causal, Arc::new(Linear));
+        conv.weights.data = vec![1.0, 10.0];
+        conv.biases.data = vec![0.0];
+
+        let input = sequence(1, &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let output = conv.feed_forward(&input).unwrap();
+
+        // y[t] = x[t - 2] + 10 * x[t], with zeros before the sequence starts
+        assert_eq!(output.cols, 5);
+        assert_eq!(output.data, vec![10.0, 20.0, 31.0, 42.0, 53.0]);
+    }
+
+    #[test]
+    fn test_valid_multi_channel_convolution() {
+        let mut conv = Conv1D::new(2, 1, 2, 1, Padding::Valid, Arc::new(Linear));
+        conv.weights.data = vec![1.0, 2.0, 3.0, 4.0];
+        conv.biases.data = vec![0.5];
+
+        let input = sequence(2, &[
+            1.0, 2.0, 3.0,
+            1.0, 0.0, 1.0,
+        ]);
+        let output = conv.feed_forward(&input).unwrap();
+
+        assert_eq!((output.rows, output.cols), (1, 2));
+        assert_eq!(output.data, vec![1.0 + 4.0 + 3.0 + 0.0 + 0.5, 2.0 + 6.0 + 0.0 + 4.0 + 0.5]);
+    }
+
+    #[test]
+    fn test_backpropagate_matches_finite_difference() {
+        let mut conv = Conv1D::new(2, 3, 3, 2, Padding::Causal, Arc::new(Sigmoid));
+        let input = sequence(2, &[0.1, -0.4, 0.3, 0.8, -0.2, 0.5, 0.9, -0.7, 0.2, 0.0, 0.4, -0.1]);
+
+        assert!(gradcheck::check_module(&mut conv, &input).unwrap().passed(1e-6));
+        assert!(gradcheck::check_module_parameters(&mut conv, &input, |c| &mut c.weights).unwrap().passed(1e-5));
+        assert!(gradcheck::check_module_parameters(&mut conv, &input, |c| &mut c.biases).unwrap().passed(1e-5));
+    }
+
+    #[test]
+    fn test_rejects_short_sequences() {
+        let mut conv = Conv1D::new(1, 1, 3, 2, Padding::Valid, Arc::new(ReLU));
+        assert!(conv.feed_forward(&sequence(1, &[1.0, 2.0, 3.0, 4.0])).is_err());
+        assert!(conv.feed_forward(&sequence(1, &[1.0, 2.0, 3.0, 4.0, 5.0])).is_ok());
+    }
+}
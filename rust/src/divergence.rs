@@ -0,0 +1,79 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DivergencePolicy {
+    // Stop training and return `TrainingError::Diverged`.
+    Abort,
+    // Restore the weights from the end of the last good epoch and stop training.
+    Rollback,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteSource {
+    Activation,
+    Gradient,
+    Weight,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub source: NonFiniteSource,
+    pub layer: usize,
+    // 1-based, matching the epoch numbers printed by `fit`.
+    pub epoch: usize,
+    // Index of the training sample in the data passed to `fit`.
+    pub sample: usize,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let source = match self.source {
+            NonFiniteSource::Activation => "activation",
+            NonFiniteSource::Gradient => "gradient",
+            NonFiniteSource::Weight => "weight",
+        };
+        write!(f, "Non-finite {} in layer {} at epoch {}, sample {}", source, self.layer, self.epoch, self.sample)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrainingError {
+    Invalid(&'static str),
+    Diverged(Divergence),
+    // Writing a checkpoint failed; holds the I/O error message.
+    Checkpoint(String),
+}
+
+impl From<&'static str> for TrainingError {
+    fn from(message: &'static str) -> Self {
+        TrainingError::Invalid(message)
+    }
+}
+
+impl fmt::Display for TrainingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrainingError::Invalid(message) => write!(f, "{}", message),
+            TrainingError::Diverged(divergence) => write!(f, "Training diverged: {}", divergence),
+            TrainingError::Checkpoint(message) => write!(f, "Failed to write checkpoint: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TrainingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let divergence = Divergence { source: NonFiniteSource::Gradient, layer: 2, epoch: 5, sample: 17 };
+        assert_eq!(divergence.to_string(), "Non-finite gradient in layer 2 at epoch 5, sample 17");
+        assert_eq!(
+            TrainingError::Diverged(divergence).to_string(),
+            "Training diverged: Non-finite gradient in layer 2 at epoch 5, sample 17"
+        );
+        assert_eq!(TrainingError::from("Invalid input/target data").to_string(), "Invalid input/target data");
+    }
+}
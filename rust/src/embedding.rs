@@ -0,0 +1,144 @@
+use crate::matrix::Matrix;
+use std::collections::HashMap;
+
+// Maps integer ids to trainable vectors. Each row of `weights` is one id's
+// embedding, so lookups and updates only touch the rows that were used.
+pub struct Embedding {
+    pub vocab_size: usize,
+    pub embedding_dim: usize,
+    pub weights: Matrix,
+    input_shape: Option<(usize, usize)>,
+    last_ids: Vec<usize>,
+}
+
+impl Embedding {
+    pub fn new(vocab_size: usize, embedding_dim: usize) -> Self {
+        let mut weights = Matrix::new(vocab_size, embedding_dim);
+        weights.randomize();
+
+        Embedding {
+            vocab_size,
+            embedding_dim,
+            weights,
+            input_shape: None,
+            last_ids: Vec::new(),
+        }
+    }
+
+    // Returns an `embedding_dim x ids.len()` matrix, one column per id.
+    pub fn lookup(&mut self, ids: &[usize]) -> Result<Matrix, &'static str> {
+        if ids.iter().any(|&id| id >= self.vocab_size) {
+            return Err("Embedding id out of range");
+        }
+
+        let len = ids.len();
+        let mut output = Matrix::new(self.embedding_dim, len);
+        for (t, &id) in ids.iter().enumerate() {
+            let row = &self.weights.data[id * self.embedding_dim..(id + 1) * self.embedding_dim];
+            for (d, &value) in row.iter().enumerate() {
+                output.data[d * len + t] = value;
+            }
+        }
+
+        self.input_shape = Some((ids.len(), 1));
+        self.last_ids = ids.to_vec();
+        Ok(output)
+    }
+
+    // Ids are read from `input` in row-major order and must be whole numbers.
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        let ids = input.data.iter()
+            .map(|&v| {
+                if v < 0.0 || v.fract() != 0.0 {
+                    Err("Embedding ids must be non-negative integers")
+                } else {
+                    Ok(v as usize)
+                }
+            })
+            .collect::<Result<Vec<usize>, &'static str>>()?;
+
+        let output = self.lookup(&ids)?;
+        self.input_shape = Some((input.rows, input.cols));
+        Ok(output)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.weights]
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let (rows, cols) = self.input_shape.ok_or("No input stored for backpropagation")?;
+        let len = self.last_ids.len();
+        if output_error.rows != self.embedding_dim || output_error.cols != len {
+            return Err("Matrix size mismatch");
+        }
+
+        // Sum the gradient per id first so repeated ids get a single update
+        let mut row_gradients: HashMap<usize, Vec<f64>> = HashMap::new();
+        for (t, &id) in self.last_ids.iter().enumerate() {
+            let gradient = row_gradients.entry(id).or_insert_with(|| vec![0.0; self.embedding_dim]);
+            for (d, g) in gradient.iter_mut().enumerate() {
+                *g += output_error.data[d * len + t];
+            }
+        }
+
+        for (id, gradient) in row_gradients {
+            let row = &mut self.weights.data[id * self.embedding_dim..(id + 1) * self.embedding_dim];
+            for (w, g) in row.iter_mut().zip(gradient) {
+                *w += learning_rate * g;
+            }
+        }
+
+        // Ids are not differentiable, so nothing flows back to the input
+        Ok(Matrix::new(rows, cols))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let mut embedding = Embedding::new(4, 2);
+        embedding.weights.data = vec![0.0, 0.1, 1.0, 1.1, 2.0, 2.1, 3.0, 3.1];
+
+        let output = embedding.lookup(&[2, 0, 2]).unwrap();
+        assert_eq!((output.rows, output.cols), (2, 3));
+        assert_eq!(output.data, vec![2.0, 0.0, 2.0, 2.1, 0.1, 2.1]);
+
+        assert!(embedding.lookup(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_feed_forward_from_matrix() {
+        let mut embedding = Embedding::new(3, 2);
+        let ids = Matrix::from_array(&[1.0, 2.0]);
+        let output = embedding.feed_forward(&ids).unwrap();
+        assert_eq!(output.get(0, 0), embedding.weights.get(1, 0));
+        assert_eq!(output.get(1, 1), embedding.weights.get(2, 1));
+
+        assert!(embedding.feed_forward(&Matrix::from_array(&[0.5])).is_err());
+        assert!(embedding.feed_forward(&Matrix::from_array(&[-1.0])).is_err());
+    }
+
+    #[test]
+    fn test_sparse_update_only_touches_used_rows() {
+        let mut embedding = Embedding::new(5, 2);
+        let before = embedding.weights.clone();
+
+        embedding.lookup(&[3, 1, 3]).unwrap();
+        let error = Matrix { rows: 2, cols: 3, data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0] };
+        let input_error = embedding.backpropagate(&error, 0.5).unwrap();
+        assert_eq!((input_error.rows, input_error.cols), (3, 1));
+
+        for id in [0, 2, 4] {
+            assert_eq!(embedding.weights.get(id, 0), before.get(id, 0));
+            assert_eq!(embedding.weights.get(id, 1), before.get(id, 1));
+        }
+        assert!((embedding.weights.get(1, 0) - (before.get(1, 0) + 1.0)).abs() < 1e-12);
+        assert!((embedding.weights.get(1, 1) - (before.get(1, 1) + 2.5)).abs() < 1e-12);
+        assert!((embedding.weights.get(3, 0) - (before.get(3, 0) + 2.0)).abs() < 1e-12);
+        assert!((embedding.weights.get(3, 1) - (before.get(3, 1) + 5.0)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::matrix::Matrix;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Element counts above which `Matrix` splits an operation across threads.
+// `product` and `transpose` count elements of the result, `reduction` (sums,
+// norms, maxima) elements of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    pub product: usize,
+    pub elementwise: usize,
+    pub transpose: usize,
+    pub reduction: usize,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        // Lower in tests so the parallel paths run on small matrices too
+        Thresholds::uniform(if cfg!(test) { 100 } else { 1000 })
+    }
+}
+
+impl Thresholds {
+    pub fn uniform(threshold: usize) -> Self {
+        Thresholds { product: threshold, elementwise: threshold, transpose: threshold, reduction: threshold }
+    }
+
+    // Never splits anything.
+    pub fn sequential() -> Self {
+        Thresholds::uniform(usize::MAX)
+    }
+}
+
+// Thresholds shared by every worker of a context's pool, so they can be
+// changed after the threads have started.
+#[derive(Debug)]
+struct Shared {
+    product: AtomicUsize,
+    elementwise: AtomicUsize,
+    transpose: AtomicUsize,
+    reduction: AtomicUsize,
+}
+
+impl Shared {
+    fn load(&self) -> Thresholds {
+        Thresholds {
+            product: self.product.load(Ordering::Relaxed),
+            elementwise: self.elementwise.load(Ordering::Relaxed),
+            transpose: self.transpose.load(Ordering::Relaxed),
+            reduction: self.reduction.load(Ordering::Relaxed),
+        }
+    }
+
+    fn store(&self, thresholds: Thresholds) {
+        self.product.store(thresholds.product, Ordering::Relaxed);
+        self.elementwise.store(thresholds.elementwise, Ordering::Relaxed);
+        self.transpose.store(thresholds.transpose, Ordering::Relaxed);
+        self.reduction.store(thresholds.reduction, Ordering::Relaxed);
+    }
+}
+
+thread_local! {
+    // Set on the workers of a context's pool; everywhere else is `None` and
+    // the defaults apply.
+    static CURRENT: RefCell<Option<Arc<Shared>>> = const { RefCell::new(None) };
+}
+
+// Thresholds in effect on the calling thread.
+pub(crate) fn thresholds() -> Thresholds {
+    CURRENT.with(|current| current.borrow().as_ref().map(|shared| shared.load()))
+        .unwrap_or_default()
+}
+
+// Matrix sides tried by `auto_tuned`; products get costly sooner.
+const PRODUCT_SIZES: &[usize] = &[8, 16, 32, 64, 128, 256];
+const SIZES: &[usize] = &[16, 32, 64, 128, 256, 512, 1024];
+
+// A dedicated rayon pool plus the thresholds used on it. Outside `install` the
+// crate runs on rayon's global pool with the default thresholds, so embedding
+// services can keep this crate's work off the pool their own code uses.
+// Clones share the same pool and thresholds.
+#[derive(Clone, Debug)]
+pub struct ExecutionContext {
+    pool: Arc<ThreadPool>,
+    shared: Arc<Shared>,
+}
+
+impl ExecutionContext {
+    // `threads` of 0 lets rayon pick, normally one per CPU.
+    pub fn new(threads: usize, thresholds: Thresholds) -> Result<Self, &'static str> {
+        let shared = Arc::new(Shared {
+            product: AtomicUsize::new(thresholds.product),
+            elementwise: AtomicUsize::new(thresholds.elementwise),
+            transpose: AtomicUsize::new(thresholds.transpose),
+            reduction: AtomicUsize::new(thresholds.reduction),
+        });
+        let worker_shared = Arc::clone(&shared);
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("neural-network-{}", index))
+            .start_handler(move |_| {
+                CURRENT.with(|current| *current.borrow_mut() = Some(Arc::clone(&worker_shared)));
+            })
+            .build()
+            .map_err(|_| "Failed to build thread pool")?;
+        Ok(ExecutionContext { pool: Arc::new(pool), shared })
+    }
+
+    // Like `new`, but the thresholds are measured on the new pool: each
+    // operation is timed serially and split at growing sizes, and parallelism
+    // starts where it keeps winning. Takes a few tens of milliseconds.
+    pub fn auto_tuned(threads: usize) -> Result<Self, &'static str> {
+        let context = ExecutionContext::new(threads, Thresholds::sequential())?;
+        if context.threads() > 1 {
+            let thresholds = context.install(|| context.measure());
+            context.set_thresholds(thresholds);
+        }
+        Ok(context)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        self.shared.load()
+    }
+
+    // Takes effect on the pool immediately, including for clones.
+    pub fn set_thresholds(&self, thresholds: Thresholds) {
+        self.shared.store(thresholds);
+    }
+
+    // Runs `op` on this context's pool. Parallel work started inside, including
+    // by `Matrix`, stays on the pool and uses its thresholds.
+    pub fn install<R, F>(&self, op: F) -> R
+    where
+        F: FnOnce() -> R + Send,
+        R: Send,
+    {
+        self.pool.install(op)
+    }
+
+    fn measure(&self) -> Thresholds {
+        let product = self.crossover(PRODUCT_SIZES, |n| {
+            let a = Matrix::<f64>::zeros(n, n);
+            let b = Matrix::zeros(n, n);
+            let mut out = Matrix::zeros(n, n);
+            move || Matrix::dot_into(&a, &b, &mut out).unwrap()
+        });
+        let elementwise = self.crossover(SIZES, |n| {
+            let mut a = Matrix::<f64>::zeros(n, n);
+            let b = Matrix::zeros(n, n);
+            move || {
+                a.add_assign(&b).unwrap();
+                a.exp_in_place();
+            }
+        });
+        let transpose = self.crossover(SIZES, |n| {
+            let a = Matrix::<f64>::zeros(n, n);
+            move || {
+                Matrix::transpose(&a);
+            }
+        });
+        let reduction = self.crossover(SIZES, |n| {
+            let a = Matrix::<f64>::zeros(n, n);
+            move || {
+                a.l2_norm();
+                a.col_sums();
+            }
+        });
+        self.set_thresholds(Thresholds::sequential());
+        Thresholds { product, elementwise, transpose, reduction }
+    }
+
+    // Times `n x n` workloads from `setup` with every operation forced serial,
+    // then forced parallel. The threshold sits just below the smallest size
+    // from which the parallel run wins at every larger size tried.
+    fn crossover<S, W>(&self, sizes: &[usize], setup: S) -> usize
+    where
+        S: Fn(usize) -> W,
+        W: FnMut(),
+    {
+        let mut threshold = usize::MAX;
+        for &n in sizes.iter().rev() {
+            let mut work = setup(n);
+            self.set_thresholds(Thresholds::sequential());
+            let serial = best_of(&mut work);
+            self.set_thresholds(Thresholds::uniform(0));
+            let parallel = best_of(&mut work);
+            if parallel >= serial {
+                break;
+            }
+            threshold = n * n - 1;
+        }
+        threshold
+    }
+}
+
+fn best_of<W: FnMut()>(work: &mut W) -> Duration {
+    work();
+    (0..3).map(|_| {
+        let start = Instant::now();
+        work();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_work_runs_on_dedicated_pool() {
+        let context = ExecutionContext::new(2, Thresholds::uniform(7)).unwrap();
+        assert_eq!(context.threads(), 2);
+        assert_eq!(thresholds(), Thresholds::default());
+
+        let (threads, seen) = context.install(|| {
+            let seen: Vec<Thresholds> = (0..64).into_par_iter().map(|_| thresholds()).collect();
+            (rayon::current_num_threads(), seen)
+        });
+        assert_eq!(threads, 2);
+        assert!(seen.iter().all(|&t| t == Thresholds::uniform(7)));
+
+        context.clone().set_thresholds(Thresholds::sequential());
+        assert_eq!(context.install(thresholds), Thresholds::sequential());
+    }
+
+    #[test]
+    fn test_thresholds_do_not_change_results() {
+        let mut a = Matrix::new(40, 30);
+        let mut b = Matrix::new(30, 40);
+        a.randomize();
+        b.randomize();
+        let run = |thresholds| {
+            let context = ExecutionContext::new(2, thresholds).unwrap();
+            context.install(|| {
+                let mut product = Matrix::dot(&a, &b).unwrap();
+                product.add_assign(&Matrix::transpose(&product)).unwrap();
+                product.apply_in_place(|v| v * 0.5);
+                product.exp_in_place();
+                product
+            })
+        };
+
+        let serial = run(Thresholds::sequential());
+        assert_eq!(run(Thresholds::uniform(0)), serial);
+        assert_eq!(run(Thresholds { product: 0, elementwise: usize::MAX, transpose: 0, reduction: 0 }), serial);
+    }
+
+    #[test]
+    fn test_auto_tuned() {
+        // A single thread has nothing to gain from splitting
+        let single = ExecutionContext::auto_tuned(1).unwrap();
+        assert_eq!(single.thresholds(), Thresholds::sequential());
+
+        let tuned = ExecutionContext::auto_tuned(2).unwrap();
+        assert_eq!(tuned.threads(), 2);
+        let thresholds = tuned.thresholds();
+
+        // Each threshold sits just below a measured size, or never splits
+        let candidate = |threshold: usize, sizes: &[usize]| {
+            threshold == usize::MAX || sizes.iter().any(|&n| threshold == n * n - 1)
+        };
+        assert!(candidate(thresholds.product, PRODUCT_SIZES), "{:?}", thresholds);
+        assert!(candidate(thresholds.elementwise, SIZES), "{:?}", thresholds);
+        assert!(candidate(thresholds.transpose, SIZES), "{:?}", thresholds);
+        assert!(candidate(thresholds.reduction, SIZES), "{:?}", thresholds);
+
+        // Tuning moves work between threads, never changes what is computed
+        let mut a = Matrix::new(40, 30);
+        let mut b = Matrix::new(30, 40);
+        a.randomize();
+        b.randomize();
+        let run = |context: &ExecutionContext| context.install(|| {
+            let mut product = Matrix::dot(&a, &b).unwrap();
+            product.add_assign(&Matrix::transpose(&product)).unwrap();
+            product.exp_in_place();
+            (product.clone(), product.col_max().unwrap())
+        });
+        let default = ExecutionContext::new(2, Thresholds::default()).unwrap();
+        assert_eq!(run(&tuned), run(&default));
+    }
+}
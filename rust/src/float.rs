@@ -0,0 +1,133 @@
+use crate::gemm::Element;
+use crate::simd::Simd;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// Element type of matrices and networks. Implemented for f32 and f64; f32 halves
+// the memory footprint at the cost of precision. Losses, metrics and penalties
+// are still reported as f64 whatever the element type.
+pub trait Float:
+    Element
+    + Simd
+    + Copy
+    + Send
+    + Sync
+    + PartialOrd
+    + Default
+    + fmt::Debug
+    + fmt::Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const NEG_INFINITY: Self;
+    const NAN: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn exp(self) -> Self;
+    fn tanh(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn ln(self) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn abs(self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn clamp(self, min: Self, max: Self) -> Self;
+    fn is_finite(self) -> bool;
+    
+    // Bit pattern widened to 64 bits, for storing in an `AtomicU64`.
+    fn to_bits(self) -> u64;
+    fn from_bits(bits: u64) -> Self;
+}
+
+macro_rules! impl_float {
+    ($($t:ty => $bits:ty),*) => {
+        $(
+            impl Float for $t {
+                const ZERO: Self = 0.0;
+                const ONE: Self = 1.0;
+                const NEG_INFINITY: Self = <$t>::NEG_INFINITY;
+                const NAN: Self = <$t>::NAN;
+
+                #[inline]
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+
+                #[inline]
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                #[inline]
+                fn exp(self) -> Self {
+                    <$t>::exp(self)
+                }
+
+                #[inline]
+                fn tanh(self) -> Self {
+                    <$t>::tanh(self)
+                }
+
+                #[inline]
+                fn sqrt(self) -> Self {
+                    <$t>::sqrt(self)
+                }
+
+                #[inline]
+                fn ln(self) -> Self {
+                    <$t>::ln(self)
+                }
+
+                #[inline]
+                fn powf(self, exponent: Self) -> Self {
+                    <$t>::powf(self, exponent)
+                }
+
+                #[inline]
+                fn abs(self) -> Self {
+                    <$t>::abs(self)
+                }
+
+                #[inline]
+                fn max(self, other: Self) -> Self {
+                    <$t>::max(self, other)
+                }
+
+                #[inline]
+                fn clamp(self, min: Self, max: Self) -> Self {
+                    <$t>::clamp(self, min, max)
+                }
+
+                #[inline]
+                fn is_finite(self) -> bool {
+                    <$t>::is_finite(self)
+                }
+
+                #[inline]
+                fn to_bits(self) -> u64 {
+                    u64::from(<$t>::to_bits(self))
+                }
+
+                #[inline]
+                fn from_bits(bits: u64) -> Self {
+                    <$t>::from_bits(bits as $bits)
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32 => u32, f64 => u64);
@@ -0,0 +1,246 @@
+// Blocked matrix multiply, C += A * B, in the style of BLIS/GotoBLAS.
+//
+// B is packed into NR-wide column panels of KC rows and A into MR-tall row
+// panels, so the micro-kernel streams both operands contiguously while an
+// MR x NR block of C lives in registers. Blocks of MC rows of C are
+// independent and run in parallel. Operands are read through row/column
+// strides, so transposed inputs are packed directly without a copy.
+use crate::float::Float;
+use crate::simd;
+use rayon::prelude::*;
+use std::cell::Cell;
+use std::thread::LocalKey;
+
+const MR: usize = 4;
+const NR: usize = 8;
+// KC * NR doubles of B fit in L1, MC * KC of A in L2.
+const KC: usize = 256;
+const MC: usize = 96;
+const NC: usize = 2048;
+
+// Below this many multiply-adds packing costs more than it saves.
+const SMALL_WORK: usize = 32 * 32 * 32;
+
+// Packing buffers kept per thread so repeated products don't allocate.
+// They are taken out while in use, so a nested product on the same thread
+// (e.g. through rayon work stealing) just starts with an empty buffer.
+pub struct Packing<T> {
+    a: Cell<Vec<T>>,
+    b: Cell<Vec<T>>,
+}
+
+// Element types with their own packing buffers; a supertrait of `Float`.
+pub trait Element: Sized + 'static {
+    fn packing() -> &'static LocalKey<Packing<Self>>;
+}
+
+macro_rules! impl_element {
+    ($($t:ty => $key:ident),*) => {
+        thread_local! {
+            $(static $key: Packing<$t> = const { Packing { a: Cell::new(Vec::new()), b: Cell::new(Vec::new()) } };)*
+        }
+
+        $(
+            impl Element for $t {
+                fn packing() -> &'static LocalKey<Packing<Self>> {
+                    &$key
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(f32 => PACKING_F32, f64 => PACKING_F64);
+
+#[derive(Clone, Copy)]
+pub(crate) struct View<'a, T> {
+    pub data: &'a [T],
+    pub row_stride: usize,
+    pub col_stride: usize,
+}
+
+impl<'a, T: Float> View<'a, T> {
+    pub fn new(data: &'a [T], cols: usize) -> Self {
+        View { data, row_stride: cols, col_stride: 1 }
+    }
+
+    // The transpose of a row-major matrix with `cols` columns, read in place.
+    pub fn transposed(data: &'a [T], cols: usize) -> Self {
+        View { data, row_stride: 1, col_stride: cols }
+    }
+
+    #[inline(always)]
+    fn get(&self, row: usize, col: usize) -> T {
+        self.data[row * self.row_stride + col * self.col_stride]
+    }
+}
+
+// Accumulates the `m x k` by `k x n` product into the row-major `m x n` slice `c`.
+pub(crate) fn gemm<T: Float>(m: usize, n: usize, k: usize, a: View<T>, b: View<T>, c: &mut [T], parallel: bool) {
+    if m == 0 || n == 0 || k == 0 {
+        return;
+    }
+    if m * n * k <= SMALL_WORK {
+        small_gemm(m, n, k, a, b, c);
+        return;
+    }
+
+    let mut packed_b = T::packing().with(|p| p.b.take());
+    for jc in (0..n).step_by(NC) {
+        let nc = NC.min(n - jc);
+        for pc in (0..k).step_by(KC) {
+            let kc = KC.min(k - pc);
+            pack_b(b, pc, kc, jc, nc, &mut packed_b);
+
+            let block = |(index, rows): (usize, &mut [T])| {
+                let ic = index * MC;
+                let mc = rows.len() / n;
+                let mut packed_a = T::packing().with(|p| p.a.take());
+                pack_a(a, ic, mc, pc, kc, &mut packed_a);
+                macro_kernel(rows, n, mc, jc, nc, kc, &packed_a, &packed_b);
+                T::packing().with(|p| p.a.set(packed_a));
+            };
+
+            if parallel {
+                c.par_chunks_mut(MC * n).enumerate().for_each(block);
+            } else {
+                c.chunks_mut(MC * n).enumerate().for_each(block);
+            }
+        }
+    }
+    T::packing().with(|p| p.b.set(packed_b));
+}
+
+// i-p-j order: the inner loop runs along a row of B and of C.
+fn small_gemm<T: Float>(m: usize, n: usize, k: usize, a: View<T>, b: View<T>, c: &mut [T]) {
+    for (i, c_row) in c.chunks_exact_mut(n).enumerate().take(m) {
+        for p in 0..k {
+            let a_ip = a.get(i, p);
+            for (j, value) in c_row.iter_mut().enumerate() {
+                *value += a_ip * b.get(p, j);
+            }
+        }
+    }
+}
+
+// Rows `ic..ic + mc`, columns `pc..pc + kc` of A as MR-row panels, each stored
+// column by column and zero-padded to a full MR rows.
+fn pack_a<T: Float>(a: View<T>, ic: usize, mc: usize, pc: usize, kc: usize, packed: &mut Vec<T>) {
+    let panels = mc.div_ceil(MR);
+    packed.clear();
+    packed.resize(panels * MR * kc, T::ZERO);
+    for (panel, chunk) in packed.chunks_exact_mut(MR * kc).enumerate() {
+        let rows = MR.min(mc - panel * MR);
+        for (p, column) in chunk.chunks_exact_mut(MR).enumerate() {
+            for (ii, value) in column.iter_mut().enumerate().take(rows) {
+                *value = a.get(ic + panel * MR + ii, pc + p);
+            }
+        }
+    }
+}
+
+// Rows `pc..pc + kc`, columns `jc..jc + nc` of B as NR-column panels, each
+// stored row by row and zero-padded to a full NR columns.
+fn pack_b<T: Float>(b: View<T>, pc: usize, kc: usize, jc: usize, nc: usize, packed: &mut Vec<T>) {
+    let panels = nc.div_ceil(NR);
+    packed.clear();
+    packed.resize(panels * NR * kc, T::ZERO);
+    for (panel, chunk) in packed.chunks_exact_mut(NR * kc).enumerate() {
+        let cols = NR.min(nc - panel * NR);
+        for (p, row) in chunk.chunks_exact_mut(NR).enumerate() {
+            for (jj, value) in row.iter_mut().enumerate().take(cols) {
+                *value = b.get(pc + p, jc + panel * NR + jj);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn macro_kernel<T: Float>(c: &mut [T], ldc: usize, mc: usize, jc: usize, nc: usize, kc: usize, packed_a: &[T], packed_b: &[T]) {
+    for (jp, b_panel) in packed_b.chunks_exact(NR * kc).enumerate() {
+        let j0 = jc + jp * NR;
+        let cols = NR.min(jc + nc - j0);
+        for (ip, a_panel) in packed_a.chunks_exact(MR * kc).enumerate() {
+            let i0 = ip * MR;
+            let rows = MR.min(mc - i0);
+
+            let mut acc = [[T::ZERO; NR]; MR];
+            micro_kernel(a_panel, b_panel, &mut acc);
+
+            for (i, acc_row) in acc.iter().enumerate().take(rows) {
+                let start = (i0 + i) * ldc + j0;
+                for (value, &a) in c[start..start + cols].iter_mut().zip(acc_row) {
+                    *value += a;
+                }
+            }
+        }
+    }
+}
+
+// Rank-1 updates of an MR x NR accumulator, with FMA on the widest
+// instruction set the CPU supports.
+#[inline(always)]
+fn micro_kernel<T: Float>(a_panel: &[T], b_panel: &[T], acc: &mut [[T; NR]; MR]) {
+    simd::outer_products(a_panel, b_panel, acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(len: usize, seed: usize) -> Vec<f64> {
+        (0..len).map(|i| ((i * 31 + seed * 17) % 23) as f64 / 11.0 - 1.0).collect()
+    }
+
+    fn reference(m: usize, n: usize, k: usize, a: View<f64>, b: View<f64>) -> Vec<f64> {
+        let mut c = vec![0.0; m * n];
+        for i in 0..m {
+            for j in 0..n {
+                c[i * n + j] = (0..k).map(|p| a.get(i, p) * b.get(p, j)).sum();
+            }
+        }
+        c
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9 * (1.0 + e.abs()), "{} vs {}", a, e);
+        }
+    }
+
+    #[test]
+    fn test_matches_reference_on_ragged_shapes() {
+        // Edges that are not multiples of MR, NR, MC or KC, and a k spanning two KC blocks
+        for &(m, n, k) in &[(1, 1, 1), (3, 1, 70), (5, 9, 40), (97, 13, 300), (130, 37, 65), (200, 8, 257)] {
+            let (a, b) = (values(m * k, 1), values(k * n, 2));
+            let (a, b) = (View::new(&a, k), View::new(&b, n));
+            let expected = reference(m, n, k, a, b);
+
+            for parallel in [false, true] {
+                let mut c = vec![0.0; m * n];
+                gemm(m, n, k, a, b, &mut c, parallel);
+                assert_close(&c, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transposed_views() {
+        let (m, n, k) = (70, 45, 90);
+        // Stored as k x m and n x k, read as their transposes
+        let (a, b) = (values(k * m, 3), values(n * k, 4));
+        let (a, b) = (View::transposed(&a, m), View::transposed(&b, k));
+
+        let mut c = vec![0.0; m * n];
+        gemm(m, n, k, a, b, &mut c, true);
+        assert_close(&c, &reference(m, n, k, a, b));
+    }
+
+    #[test]
+    fn test_accumulates_into_output() {
+        let (a, b) = (vec![1.0, 2.0, 3.0, 4.0], vec![1.0, 0.0, 0.0, 1.0]);
+        let mut c = vec![10.0; 4];
+        gemm(2, 2, 2, View::new(&a, 2), View::new(&b, 2), &mut c, false);
+        assert_eq!(c, vec![11.0, 12.0, 13.0, 14.0]);
+    }
+}
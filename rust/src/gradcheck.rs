@@ -0,0 +1,227 @@
+// Numerical gradient checks. Analytic gradients from the backward pass are
+// compared against central finite differences of a scalar loss.
+//
+// Modules are checked against loss = sum(probe * output) for a fixed, uneven
+// `probe`, so the error fed to `backpropagate` is just `probe`.
+use crate::activation::ActivationFunction;
+use crate::loss::LossFunction;
+use crate::matrix::Matrix;
+use crate::module::Module;
+
+const EPSILON: f64 = 1e-6;
+
+// Below this magnitude gradients are compared by absolute rather than relative
+// error, so values that are zero up to rounding noise don't fail the check.
+const RELATIVE_ERROR_FLOOR: f64 = 1e-3;
+
+// Learning rate for the single step used to read parameter gradients off an
+// update. Small enough that later sub-layers stepping first barely perturbs
+// the error reaching earlier ones.
+const PROBE_LEARNING_RATE: f64 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradCheck {
+    pub max_relative_error: f64,
+    pub max_absolute_error: f64,
+    pub checked: usize,
+}
+
+impl GradCheck {
+    pub fn passed(&self, tolerance: f64) -> bool {
+        self.max_relative_error <= tolerance
+    }
+}
+
+pub fn compare(analytic: &[f64], numeric: &[f64]) -> GradCheck {
+    let mut report = GradCheck { max_relative_error: 0.0, max_absolute_error: 0.0, checked: analytic.len() };
+    for (&a, &n) in analytic.iter().zip(numeric) {
+        let absolute = (a - n).abs();
+        let relative = absolute / a.abs().max(n.abs()).max(RELATIVE_ERROR_FLOOR);
+        // NaN never compares greater, so record it explicitly
+        if relative.is_nan() || relative > report.max_relative_error {
+            report.max_relative_error = relative;
+        }
+        if absolute.is_nan() || absolute > report.max_absolute_error {
+            report.max_absolute_error = absolute;
+        }
+    }
+    report
+}
+
+// Central differences of `f` around `point`.
+pub fn numeric_gradient<F>(point: &[f64], mut f: F) -> Vec<f64>
+where
+    F: FnMut(&[f64]) -> f64,
+{
+    let mut shifted = point.to_vec();
+    (0..point.len())
+        .map(|i| {
+            shifted[i] = point[i] + EPSILON;
+            let plus = f(&shifted);
+            shifted[i] = point[i] - EPSILON;
+            let minus = f(&shifted);
+            shifted[i] = point[i];
+            (plus - minus) / (2.0 * EPSILON)
+        })
+        .collect()
+}
+
+pub fn probe(rows: usize, cols: usize) -> Matrix {
+    let data = (0..rows * cols).map(|i| ((i * 7 % 11) as f64 - 5.0) * 0.13 + 0.05).collect();
+    Matrix { rows, cols, data }
+}
+
+fn probe_loss(output: &Matrix, probe: &Matrix) -> f64 {
+    output.data.iter().zip(&probe.data).map(|(o, p)| o * p).sum()
+}
+
+// Checks the error `backpropagate` returns for the module's input. Runs the
+// backward pass with a zero learning rate, so parameters are left untouched.
+pub fn check_module<M: Module + ?Sized>(module: &mut M, input: &Matrix) -> Result<GradCheck, &'static str> {
+    let output = module.feed_forward(input)?;
+    let probe = probe(output.rows, output.cols);
+
+    let mut failure = None;
+    let numeric = numeric_gradient(&input.data, |data| {
+        let shifted = Matrix { rows: input.rows, cols: input.cols, data: data.to_vec() };
+        match module.feed_forward(&shifted) {
+            Ok(out) => probe_loss(&out, &probe),
+            Err(e) => {
+                failure = Some(e);
+                f64::NAN
+            }
+        }
+    });
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    module.feed_forward(input)?;
+    let analytic = module.backpropagate(&probe, 0.0)?;
+    Ok(compare(&analytic.data, &numeric))
+}
+
+// Checks the gradient of one parameter matrix, picked out by `parameter`,
+// which must be one of `module.parameters_mut()`. The analytic gradient is
+// read off a single update with a tiny learning rate, after which every
+// parameter is restored, so the module is left as it was.
+pub fn check_module_parameters<M, P>(module: &mut M, input: &Matrix, parameter: P) -> Result<GradCheck, &'static str>
+where
+    M: Module + ?Sized,
+    P: Fn(&mut M) -> &mut Matrix,
+{
+    let output = module.feed_forward(input)?;
+    let probe = probe(output.rows, output.cols);
+
+    let before = parameter(module).clone();
+    let mut failure = None;
+    let numeric = numeric_gradient(&before.data, |data| {
+        parameter(module).data.copy_from_slice(data);
+        match module.feed_forward(input) {
+            Ok(out) => probe_loss(&out, &probe),
+            Err(e) => {
+                failure = Some(e);
+                f64::NAN
+            }
+        }
+    });
+    parameter(module).data.copy_from_slice(&before.data);
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    let checked: *const Matrix = parameter(module);
+    if !module.parameters_mut().iter().any(|p| std::ptr::eq(&**p, checked)) {
+        return Err("Module does not expose the checked parameter");
+    }
+    let snapshot: Vec<Matrix> = module.parameters_mut().into_iter().map(|p| p.clone()).collect();
+
+    let stepped = module.feed_forward(input)
+        .and_then(|_| module.backpropagate(&probe, PROBE_LEARNING_RATE));
+    let analytic: Vec<f64> = parameter(module).data.iter()
+        .zip(&before.data)
+        .map(|(after, before)| (after - before) / PROBE_LEARNING_RATE)
+        .collect();
+    for (p, saved) in module.parameters_mut().into_iter().zip(snapshot) {
+        *p = saved;
+    }
+    stepped?;
+
+    Ok(compare(&analytic, &numeric))
+}
+
+// Activations report their derivative in terms of the output, so the analytic
+// value at `x` is `derivative(activate(x))`.
+pub fn check_activation(activation: &dyn ActivationFunction, points: &[f64]) -> GradCheck {
+    let analytic: Vec<f64> = points.iter().map(|&x| activation.derivative(activation.activate(x))).collect();
+    let numeric: Vec<f64> = points.iter()
+        .map(|&x| numeric_gradient(&[x], |p| activation.activate(p[0]))[0])
+        .collect();
+    compare(&analytic, &numeric)
+}
+
+pub fn check_loss(loss: &dyn LossFunction, output: &[f64], target: &[f64]) -> GradCheck {
+    let analytic = loss.gradient(output, target);
+    let numeric = numeric_gradient(output, |o| loss.loss(o, target));
+    compare(&analytic, &numeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::Sigmoid;
+    use crate::layer::Layer;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_compare() {
+        let report = compare(&[1.0, 0.0, 2e-7], &[1.001, 0.0, 1e-7]);
+        assert_eq!(report.checked, 3);
+        assert!((report.max_absolute_error - 1e-3).abs() < 1e-12);
+        assert!((report.max_relative_error - 1e-3 / 1.001).abs() < 1e-12);
+
+        // Tiny gradients are judged on absolute error
+        assert!(compare(&[1e-9], &[-1e-9]).passed(1e-5));
+        assert!(!compare(&[f64::NAN], &[0.0]).passed(1.0));
+    }
+
+    // Passes the wrong input gradient back, and exposes no parameters
+    struct Doubled(Layer);
+
+    impl Module for Doubled {
+        fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+            self.0.feed_forward(input)
+        }
+
+        fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+            Ok(self.0.backpropagate(output_error, learning_rate)?.multiply(2.0))
+        }
+    }
+
+    #[test]
+    fn test_detects_wrong_gradients() {
+        let input = Matrix::from_array(&[0.3, -0.8]);
+        let mut correct = Layer::new(2, 3, Arc::new(Sigmoid));
+        assert!(check_module(&mut correct, &input).unwrap().passed(1e-6));
+
+        let mut broken = Doubled(Layer::new(2, 3, Arc::new(Sigmoid)));
+        let report = check_module(&mut broken, &input).unwrap();
+        assert!(report.max_relative_error > 0.4);
+    }
+
+    #[test]
+    fn test_parameter_check_leaves_module_unchanged() {
+        let input = Matrix::from_array(&[0.3, -0.8]);
+        let mut layer = Layer::new(2, 3, Arc::new(Sigmoid));
+        let (weights, biases) = (layer.weights.clone(), layer.biases.clone());
+
+        assert!(check_module_parameters(&mut layer, &input, |l| &mut l.weights).unwrap().passed(1e-5));
+        assert_eq!(layer.weights, weights);
+        assert_eq!(layer.biases, biases);
+
+        // Parameters the module does not expose cannot be restored
+        let mut broken = Doubled(Layer::new(2, 3, Arc::new(Sigmoid)));
+        let error = check_module_parameters(&mut broken, &input, |d| &mut d.0.weights).unwrap_err();
+        assert_eq!(error, "Module does not expose the checked parameter");
+    }
+}
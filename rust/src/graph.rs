@@ -0,0 +1,648 @@
+use crate::loss::LossFunction;
+use crate::matrix::Matrix;
+use crate::metrics::Metric;
+use crate::module::Module;
+use std::sync::Arc;
+
+pub type NodeId = usize;
+
+// Metric scores per head, by head name.
+pub type HeadMetrics = Vec<(String, Vec<(Metric, f64)>)>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Merge {
+    Add,
+    // Stacks the inputs' rows, in the order the inputs were given.
+    Concatenate,
+    Multiply,
+}
+
+enum Op {
+    Input(usize),
+    Module(Box<dyn Module>),
+    Merge(Merge),
+}
+
+struct Node {
+    op: Op,
+    inputs: Vec<NodeId>,
+    output: Option<Matrix>,
+}
+
+// A named graph output trained with its own loss, scaled by `weight` when the
+// head losses are combined. `metrics` are scored on the head's outputs after
+// every epoch of `fit`.
+pub struct Head {
+    pub name: String,
+    pub node: NodeId,
+    pub loss: Arc<dyn LossFunction>,
+    pub weight: f64,
+    pub metrics: Vec<Metric>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EpochReport {
+    pub epoch: usize,
+    // Weighted sum of the head losses plus any regularization penalty.
+    pub loss: f64,
+    // Unweighted average loss of each head, in head order.
+    pub head_losses: Vec<(String, f64)>,
+    // Each head's metrics over the training data at the end of the epoch, in
+    // head order. Empty for graphs without heads.
+    pub head_metrics: HeadMetrics,
+}
+
+// A model whose layers form a directed acyclic graph. Nodes can only take
+// inputs from nodes that already exist, so ids are always in topological order:
+// the forward pass runs through them in order and backprop runs in reverse.
+pub struct Graph {
+    nodes: Vec<Node>,
+    inputs: Vec<NodeId>,
+    outputs: Vec<NodeId>,
+    heads: Vec<Head>,
+    learning_rate: f64,
+}
+
+impl Graph {
+    pub fn new(learning_rate: f64) -> Self {
+        Graph {
+            nodes: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            heads: Vec::new(),
+            learning_rate,
+        }
+    }
+
+    pub fn add_input(&mut self, size: usize) -> NodeId {
+        self.nodes.push(Node { op: Op::Input(size), inputs: Vec::new(), output: None });
+        self.inputs.push(self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn add_node<M: Module + 'static>(&mut self, module: M, input: NodeId) -> Result<NodeId, &'static str> {
+        self.push(Op::Module(Box::new(module)), vec![input])
+    }
+
+    pub fn add_merge(&mut self, merge: Merge, inputs: &[NodeId]) -> Result<NodeId, &'static str> {
+        if inputs.len() < 2 {
+            return Err("A merge node needs at least two inputs");
+        }
+        self.push(Op::Merge(merge), inputs.to_vec())
+    }
+
+    fn push(&mut self, op: Op, inputs: Vec<NodeId>) -> Result<NodeId, &'static str> {
+        if inputs.iter().any(|&id| id >= self.nodes.len()) {
+            return Err("Unknown input node");
+        }
+        self.nodes.push(Node { op, inputs, output: None });
+        Ok(self.nodes.len() - 1)
+    }
+
+    pub fn set_outputs(&mut self, outputs: &[NodeId]) -> Result<(), &'static str> {
+        if outputs.is_empty() {
+            return Err("A graph needs at least one output");
+        }
+        if outputs.iter().any(|&id| id >= self.nodes.len()) {
+            return Err("Unknown output node");
+        }
+        self.outputs = outputs.to_vec();
+        self.heads.clear();
+        Ok(())
+    }
+
+    // Adds a named output. Once a graph has heads, its outputs are exactly its
+    // heads, in the order they were added.
+    pub fn add_head(
+        &mut self,
+        name: &str,
+        node: NodeId,
+        loss: Arc<dyn LossFunction>,
+        weight: f64,
+    ) -> Result<(), &'static str> {
+        if node >= self.nodes.len() {
+            return Err("Unknown output node");
+        }
+        if self.heads.iter().any(|h| h.name == name) {
+            return Err("Duplicate head name");
+        }
+        if self.heads.is_empty() {
+            self.outputs.clear();
+        }
+
+        self.heads.push(Head { name: name.to_string(), node, loss, weight, metrics: Vec::new() });
+        self.outputs.push(node);
+        Ok(())
+    }
+
+    pub fn set_head_metrics(&mut self, name: &str, metrics: Vec<Metric>) -> Result<(), &'static str> {
+        let head = self.heads.iter_mut().find(|h| h.name == name).ok_or("Unknown head")?;
+        head.metrics = metrics;
+        Ok(())
+    }
+
+    pub fn heads(&self) -> &[Head] {
+        &self.heads
+    }
+
+    pub fn inputs(&self) -> &[NodeId] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[NodeId] {
+        &self.outputs
+    }
+
+    pub fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    pub fn regularization_penalty(&self) -> f64 {
+        self.nodes.iter()
+            .map(|node| match &node.op {
+                Op::Module(module) => module.regularization_penalty(),
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    pub fn forward(&mut self, inputs: &[Matrix]) -> Result<Vec<Matrix>, &'static str> {
+        if inputs.len() != self.inputs.len() {
+            return Err("Expected one input matrix per graph input");
+        }
+        if self.outputs.is_empty() {
+            return Err("Graph outputs have not been set");
+        }
+
+        for id in 0..self.nodes.len() {
+            // Inputs always come from earlier nodes, so they live in `before`
+            let (before, rest) = self.nodes.split_at_mut(id);
+            let node = &mut rest[0];
+            let arguments: Vec<&Matrix> = node.inputs.iter()
+                .map(|&i| before[i].output.as_ref().ok_or("Node evaluated before its inputs"))
+                .collect::<Result<_, _>>()?;
+
+            let output = match &mut node.op {
+                Op::Input(size) => {
+                    let position = self.inputs.iter().position(|&i| i == id).unwrap();
+                    let input = &inputs[position];
+                    if input.rows != *size {
+                        return Err("Input size does not match graph input");
+                    }
+                    input.clone()
+                }
+                Op::Module(module) => module.feed_forward(arguments[0])?,
+                Op::Merge(merge) => merge_forward(*merge, &arguments)?,
+            };
+            node.output = Some(output);
+        }
+
+        Ok(self.outputs.iter().map(|&id| self.nodes[id].output.clone().unwrap()).collect())
+    }
+
+    // Pushes one error per graph output back through the graph, updating every
+    // module on the way, and returns the error for each graph input.
+    pub fn backward(&mut self, output_errors: &[Matrix]) -> Result<Vec<Matrix>, &'static str> {
+        if output_errors.len() != self.outputs.len() {
+            return Err("Expected one error matrix per graph output");
+        }
+
+        let mut errors: Vec<Option<Matrix>> = vec![None; self.nodes.len()];
+        for (&id, error) in self.outputs.iter().zip(output_errors) {
+            let output = self.nodes[id].output.as_ref().ok_or("No output stored for backpropagation")?;
+            if (error.rows, error.cols) != (output.rows, output.cols) {
+                return Err("Error matrix does not match graph output");
+            }
+            accumulate(&mut errors[id], error.clone())?;
+        }
+
+        for id in (0..self.nodes.len()).rev() {
+            let error = match errors[id].take() {
+                Some(error) => error,
+                None => continue,
+            };
+
+            let (before, rest) = self.nodes.split_at_mut(id);
+            let node = &mut rest[0];
+            let input_errors = match &mut node.op {
+                Op::Input(_) => {
+                    errors[id] = Some(error);
+                    continue;
+                }
+                Op::Module(module) => vec![module.backpropagate(&error, self.learning_rate)?],
+                Op::Merge(merge) => {
+                    let arguments: Vec<&Matrix> = node.inputs.iter()
+                        .map(|&i| before[i].output.as_ref().ok_or("No input stored for backpropagation"))
+                        .collect::<Result<_, _>>()?;
+                    merge_backward(*merge, &arguments, &error)?
+                }
+            };
+
+            for (&input, input_error) in self.nodes[id].inputs.iter().zip(input_errors) {
+                accumulate(&mut errors[input], input_error)?;
+            }
+        }
+
+        Ok(self.inputs.iter()
+            .map(|&id| {
+                let Op::Input(size) = self.nodes[id].op else { unreachable!() };
+                errors[id].take().unwrap_or_else(|| Matrix::new(size, 1))
+            })
+            .collect())
+    }
+
+    pub fn predict(&mut self, input_arrays: &[&[f64]]) -> Result<Vec<Vec<f64>>, &'static str> {
+        let inputs: Vec<Matrix> = input_arrays.iter().map(|a| Matrix::from_array(a)).collect();
+        Ok(self.forward(&inputs)?.iter().map(|m| m.to_array()).collect())
+    }
+
+    pub fn train(&mut self, input_arrays: &[&[f64]], target_arrays: &[&[f64]]) -> Result<(), &'static str> {
+        self.train_step(input_arrays, target_arrays)?;
+        Ok(())
+    }
+
+    // Runs one forward/backward pass and returns each output's loss. Graphs
+    // without heads train every output on squared error, like `NeuralNetwork`.
+    fn train_step(&mut self, input_arrays: &[&[f64]], target_arrays: &[&[f64]]) -> Result<Vec<f64>, &'static str> {
+        if target_arrays.len() != self.outputs.len() {
+            return Err("Expected one target per graph output");
+        }
+
+        let inputs: Vec<Matrix> = input_arrays.iter().map(|a| Matrix::from_array(a)).collect();
+        let outputs = self.forward(&inputs)?;
+
+        let mut losses = Vec::with_capacity(outputs.len());
+        let mut errors = Vec::with_capacity(outputs.len());
+        for (i, (output, &target)) in outputs.iter().zip(target_arrays).enumerate() {
+            if output.data.len() != target.len() {
+                return Err("Target size does not match output");
+            }
+
+            match self.heads.get(i) {
+                Some(head) => {
+                    losses.push(head.loss.loss(&output.data, target));
+                    let gradient = head.loss.gradient(&output.data, target);
+                    let error: Vec<f64> = gradient.iter().map(|g| -head.weight * g).collect();
+                    errors.push(Matrix { rows: output.rows, cols: output.cols, data: error });
+                }
+                None => {
+                    let error = Matrix::from_array(target).subtract(output)?;
+                    losses.push(error.data.iter().map(|e| e * e).sum::<f64>() / error.data.len() as f64);
+                    errors.push(error);
+                }
+            }
+        }
+
+        self.backward(&errors)?;
+        Ok(losses)
+    }
+
+    // `inputs[i]` holds one array per graph input and `targets[i]` one array per
+    // head (or output), in order. Returns a report for every epoch.
+    pub fn fit(
+        &mut self,
+        inputs: &[Vec<Vec<f64>>],
+        targets: &[Vec<Vec<f64>>],
+        epochs: usize,
+        verbose: bool,
+    ) -> Result<Vec<EpochReport>, &'static str> {
+        if inputs.is_empty() || targets.is_empty() || inputs.len() != targets.len() {
+            return Err("Invalid input/target data");
+        }
+
+        let report_frequency = if epochs < 100 {
+            10
+        } else if epochs < 1000 {
+            100
+        } else {
+            epochs / 10
+        };
+
+        let names: Vec<String> = if self.heads.is_empty() {
+            (0..self.outputs.len()).map(|i| format!("output_{}", i)).collect()
+        } else {
+            self.heads.iter().map(|h| h.name.clone()).collect()
+        };
+        let weights: Vec<f64> = if self.heads.is_empty() {
+            vec![1.0; self.outputs.len()]
+        } else {
+            self.heads.iter().map(|h| h.weight).collect()
+        };
+
+        let data_size = inputs.len();
+        let mut batch_indices: Vec<usize> = (0..data_size).collect();
+        let mut history = Vec::with_capacity(epochs);
+
+        for epoch in 0..epochs {
+            if data_size > 10 {
+                use rand::seq::SliceRandom;
+                use rand::thread_rng;
+                batch_indices.shuffle(&mut thread_rng());
+            }
+
+            let mut totals = vec![0.0; names.len()];
+            for &i in &batch_indices {
+                let input_arrays: Vec<&[f64]> = inputs[i].iter().map(|v| v.as_slice()).collect();
+                let target_arrays: Vec<&[f64]> = targets[i].iter().map(|v| v.as_slice()).collect();
+                let losses = self.train_step(&input_arrays, &target_arrays)?;
+                for (total, loss) in totals.iter_mut().zip(losses) {
+                    *total += loss;
+                }
+            }
+
+            let head_losses: Vec<(String, f64)> = names.iter().cloned()
+                .zip(totals.iter().map(|t| t / data_size as f64))
+                .collect();
+            let loss = head_losses.iter().zip(&weights).map(|((_, l), w)| l * w).sum::<f64>()
+                + self.regularization_penalty();
+            let head_metrics = self.head_metrics(inputs, targets)?;
+
+            if verbose && (epoch % report_frequency == 0 || epoch == epochs - 1) {
+                let mut per_head: Vec<String> = head_losses.iter()
+                    .map(|(name, l)| format!("{}: {:.6}", name, l))
+                    .collect();
+                for (name, metrics) in &head_metrics {
+                    for (metric, value) in metrics {
+                        per_head.push(format!("{} {}: {}", name, metric.name(), metric.format_value(*value)));
+                    }
+                }
+                println!("Epoch {}/{} - Loss: {:.6} - {}", epoch + 1, epochs, loss, per_head.join(" - "));
+            }
+
+            history.push(EpochReport { epoch: epoch + 1, loss, head_losses, head_metrics });
+        }
+
+        Ok(history)
+    }
+
+    // Scores every head's metrics on the current weights. Skips the extra
+    // forward passes when no head has any metrics.
+    fn head_metrics(
+        &mut self,
+        inputs: &[Vec<Vec<f64>>],
+        targets: &[Vec<Vec<f64>>],
+    ) -> Result<HeadMetrics, &'static str> {
+        if self.heads.iter().all(|h| h.metrics.is_empty()) {
+            return Ok(self.heads.iter().map(|h| (h.name.clone(), Vec::new())).collect());
+        }
+
+        let mut outputs = vec![Vec::with_capacity(inputs.len()); self.heads.len()];
+        for sample in inputs {
+            let input_arrays: Vec<&[f64]> = sample.iter().map(|v| v.as_slice()).collect();
+            for (head_outputs, output) in outputs.iter_mut().zip(self.predict(&input_arrays)?) {
+                head_outputs.push(output);
+            }
+        }
+
+        Ok(self.heads.iter().enumerate()
+            .map(|(h, head)| {
+                let head_targets: Vec<Vec<f64>> = targets.iter().map(|t| t[h].clone()).collect();
+                let scores = head.metrics.iter().map(|m| (*m, m.compute(&outputs[h], &head_targets))).collect();
+                (head.name.clone(), scores)
+            })
+            .collect())
+    }
+}
+
+fn accumulate(slot: &mut Option<Matrix>, error: Matrix) -> Result<(), &'static str> {
+    *slot = match slot.take() {
+        Some(existing) => Some(existing.add(&error)?),
+        None => Some(error),
+    };
+    Ok(())
+}
+
+fn merge_forward(merge: Merge, inputs: &[&Matrix]) -> Result<Matrix, &'static str> {
+    match merge {
+        Merge::Add => inputs[1..].iter().try_fold(inputs[0].clone(), |acc, m| acc.add(m)),
+        Merge::Multiply => inputs[1..].iter().try_fold(inputs[0].clone(), |acc, m| Matrix::hadamard(&acc, m)),
+        Merge::Concatenate => {
+            let cols = inputs[0].cols;
+            if inputs.iter().any(|m| m.cols != cols) {
+                return Err("Concatenated inputs must have the same number of columns");
+            }
+            let rows = inputs.iter().map(|m| m.rows).sum();
+            let data = inputs.iter().flat_map(|m| m.data.iter().copied()).collect();
+            Ok(Matrix { rows, cols, data })
+        }
+    }
+}
+
+fn merge_backward(merge: Merge, inputs: &[&Matrix], error: &Matrix) -> Result<Vec<Matrix>, &'static str> {
+    match merge {
+        Merge::Add => Ok(inputs.iter().map(|_| error.clone()).collect()),
+        Merge::Multiply => (0..inputs.len())
+            .map(|i| {
+                inputs.iter().enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .try_fold(error.clone(), |acc, (_, m)| Matrix::hadamard(&acc, m))
+            })
+            .collect(),
+        Merge::Concatenate => {
+            if error.data.len() != inputs.iter().map(|m| m.data.len()).sum::<usize>() {
+                return Err("Error does not match concatenated inputs");
+            }
+            let mut offset = 0;
+            Ok(inputs.iter()
+                .map(|m| {
+                    let len = m.rows * m.cols;
+                    let part = Matrix {
+                        rows: m.rows,
+                        cols: m.cols,
+                        data: error.data[offset..offset + len].to_vec(),
+                    };
+                    offset += len;
+                    part
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::{ActivationFunction, Linear, ReLU, Sigmoid, Tanh};
+    use crate::gradcheck;
+    use crate::layer::Layer;
+    use crate::loss::{BinaryCrossEntropy, MeanSquaredError};
+
+    #[test]
+    fn test_residual_connection() {
+        let mut graph = Graph::new(0.1);
+        let x = graph.add_input(2);
+        let mut layer = Layer::new(2, 2, Arc::new(ReLU) as Arc<dyn ActivationFunction>);
+        layer.weights.data = vec![1.0, 0.0, 0.0, 1.0];
+        layer.biases.data = vec![0.0, 0.0];
+        let hidden = graph.add_node(layer, x).unwrap();
+        let residual = graph.add_merge(Merge::Add, &[x, hidden]).unwrap();
+        graph.set_outputs(&[residual]).unwrap();
+
+        let output = graph.predict(&[&[1.0, -2.0]]).unwrap();
+        assert_eq!(output, vec![vec![2.0, -2.0]]);
+    }
+
+    #[test]
+    fn test_multi_input_multi_output() {
+        let mut graph = Graph::new(0.1);
+        let a = graph.add_input(2);
+        let b = graph.add_input(3);
+        let joined = graph.add_merge(Merge::Concatenate, &[a, b]).unwrap();
+        let shared = graph.add_node(Layer::new(5, 4, Arc::new(Tanh)), joined).unwrap();
+        let head_one = graph.add_node(Layer::new(4, 1, Arc::new(Sigmoid)), shared).unwrap();
+        let head_two = graph.add_node(Layer::new(4, 3, Arc::new(Tanh)), shared).unwrap();
+        graph.set_outputs(&[head_one, head_two]).unwrap();
+
+        let outputs = graph.predict(&[&[0.1, 0.2], &[0.3, 0.4, 0.5]]).unwrap();
+        assert_eq!(outputs[0].len(), 1);
+        assert_eq!(outputs[1].len(), 3);
+
+        assert!(graph.predict(&[&[0.1, 0.2]]).is_err());
+        assert!(graph.predict(&[&[0.1, 0.2], &[0.3]]).is_err());
+    }
+
+    #[test]
+    fn test_backward_rejects_mismatched_errors() {
+        let mut graph = Graph::new(0.1);
+        let a = graph.add_input(2);
+        let b = graph.add_input(3);
+        let joined = graph.add_merge(Merge::Concatenate, &[a, b]).unwrap();
+        graph.set_outputs(&[joined]).unwrap();
+        assert!(graph.backward(&[Matrix::new(5, 1)]).is_err());
+
+        graph.predict(&[&[0.1, 0.2], &[0.3, 0.4, 0.5]]).unwrap();
+        assert!(graph.backward(&[Matrix::new(3, 1)]).is_err());
+        assert!(graph.backward(&[Matrix::new(5, 2)]).is_err());
+        assert_eq!(graph.backward(&[Matrix::new(5, 1)]).unwrap().len(), 2);
+
+        let short = Matrix::new(2, 1);
+        assert!(merge_backward(Merge::Concatenate, &[&Matrix::new(2, 1), &Matrix::new(3, 1)], &short).is_err());
+    }
+
+    #[test]
+    fn test_backward_matches_finite_difference() {
+        let mut graph = Graph::new(0.0);
+        let a = graph.add_input(3);
+        let b = graph.add_input(3);
+        let left = graph.add_node(Layer::new(3, 3, Arc::new(Tanh)), a).unwrap();
+        let right = graph.add_node(Layer::new(3, 3, Arc::new(Sigmoid)), b).unwrap();
+        let product = graph.add_merge(Merge::Multiply, &[left, right, a]).unwrap();
+        let sum = graph.add_merge(Merge::Add, &[product, left]).unwrap();
+        let joined = graph.add_merge(Merge::Concatenate, &[sum, right]).unwrap();
+        graph.set_outputs(&[joined]).unwrap();
+
+        let inputs = [Matrix::from_array(&[0.2, -0.5, 0.7]), Matrix::from_array(&[-0.1, 0.4, 0.9])];
+        let coef = Matrix::from_array(&[0.3, -0.7, 0.5, 1.1, -0.2, 0.6]);
+        let mut loss = |inputs: &[Matrix]| -> f64 {
+            let out = &graph.forward(inputs).unwrap()[0];
+            out.data.iter().zip(&coef.data).map(|(o, c)| o * c).sum()
+        };
+
+        let flat: Vec<f64> = inputs.iter().flat_map(|m| m.data.clone()).collect();
+        let numeric = gradcheck::numeric_gradient(&flat, |x| {
+            loss(&[Matrix::from_array(&x[..3]), Matrix::from_array(&x[3..])])
+        });
+
+        graph.forward(&inputs).unwrap();
+        let input_errors = graph.backward(std::slice::from_ref(&coef)).unwrap();
+        let analytic: Vec<f64> = input_errors.iter().flat_map(|m| m.data.clone()).collect();
+        let report = gradcheck::compare(&analytic, &numeric);
+        assert!(report.passed(1e-6), "{:?}", report);
+    }
+
+    #[test]
+    fn test_training_reduces_error() {
+        let mut graph = Graph::new(0.1);
+        let x = graph.add_input(2);
+        let hidden = graph.add_node(Layer::new(2, 4, Arc::new(Tanh)), x).unwrap();
+        let skip = graph.add_merge(Merge::Concatenate, &[x, hidden]).unwrap();
+        let output = graph.add_node(Layer::new(6, 1, Arc::new(Sigmoid)), skip).unwrap();
+        graph.set_outputs(&[output]).unwrap();
+
+        let data = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 1.0)];
+        let error = |graph: &mut Graph| -> f64 {
+            data.iter()
+                .map(|(x, y)| (graph.predict(&[x]).unwrap()[0][0] - y).powi(2))
+                .sum()
+        };
+
+        let before = error(&mut graph);
+        for _ in 0..500 {
+            for (x, y) in &data {
+                graph.train(&[x], &[&[*y]]).unwrap();
+            }
+        }
+        assert!(error(&mut graph) < before);
+    }
+
+    #[test]
+    fn test_named_heads_with_weighted_losses() {
+        let mut graph = Graph::new(0.05);
+        let x = graph.add_input(2);
+        let shared = graph.add_node(Layer::new(2, 6, Arc::new(Tanh)), x).unwrap();
+        let class = graph.add_node(Layer::new(6, 1, Arc::new(Sigmoid)), shared).unwrap();
+        let value = graph.add_node(Layer::new(6, 1, Arc::new(Linear)), shared).unwrap();
+        graph.add_head("class", class, Arc::new(BinaryCrossEntropy), 1.0).unwrap();
+        graph.add_head("value", value, Arc::new(MeanSquaredError), 0.5).unwrap();
+        assert!(graph.add_head("value", value, Arc::new(MeanSquaredError), 1.0).is_err());
+        assert_eq!(graph.outputs(), &[class, value]);
+
+        let mut inputs = Vec::new();
+        let mut targets = Vec::new();
+        for i in 0..20 {
+            let a = (i % 5) as f64 / 4.0;
+            let b = (i / 5) as f64 / 3.0;
+            inputs.push(vec![vec![a, b]]);
+            targets.push(vec![vec![if a > b { 1.0 } else { 0.0 }], vec![a + b]]);
+        }
+
+        graph.set_head_metrics("class", vec![Metric::Accuracy]).unwrap();
+        graph.set_head_metrics("value", vec![Metric::MeanAbsoluteError, Metric::R2]).unwrap();
+        assert!(graph.set_head_metrics("missing", vec![Metric::Accuracy]).is_err());
+        let history = graph.fit(&inputs, &targets, 200, false).unwrap();
+        assert_eq!(history.len(), 200);
+
+        let first = &history[0];
+        let last = &history[199];
+        assert_eq!(last.head_losses[0].0, "class");
+        assert_eq!(last.head_losses[1].0, "value");
+        assert!((last.loss - (last.head_losses[0].1 + 0.5 * last.head_losses[1].1)).abs() < 1e-12);
+        assert!(last.head_losses[0].1 < first.head_losses[0].1);
+        assert!(last.head_losses[1].1 < first.head_losses[1].1);
+
+        // Metrics are scored per head, and agree with predictions after the last epoch
+        let (name, class_metrics) = &last.head_metrics[0];
+        assert_eq!(name, "class");
+        assert_eq!(class_metrics[0].0, Metric::Accuracy);
+        assert!(class_metrics[0].1 >= 0.75);
+        let value_metrics = &last.head_metrics[1].1;
+        assert_eq!(value_metrics.iter().map(|(m, _)| *m).collect::<Vec<_>>(), vec![Metric::MeanAbsoluteError, Metric::R2]);
+        assert!(value_metrics[0].1 < first.head_metrics[1].1[0].1);
+
+        let outputs: Vec<Vec<f64>> = inputs.iter().map(|x| graph.predict(&[&x[0]]).unwrap()[1].clone()).collect();
+        let value_targets: Vec<Vec<f64>> = targets.iter().map(|t| t[1].clone()).collect();
+        assert!((Metric::MeanAbsoluteError.compute(&outputs, &value_targets) - value_metrics[0].1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_head_weight_scales_error() {
+        let build = |weight: f64| {
+            let mut graph = Graph::new(1.0);
+            let x = graph.add_input(1);
+            let mut layer = Layer::new(1, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>);
+            layer.weights.data = vec![0.5];
+            layer.biases.data = vec![0.0];
+            let out = graph.add_node(layer, x).unwrap();
+            graph.add_head("out", out, Arc::new(MeanSquaredError), weight).unwrap();
+            graph
+        };
+
+        // loss = w * (0.5x - t)^2 with x = 1, t = 0 gives a weight gradient of w
+        for weight in [0.0, 0.25, 1.0] {
+            let mut graph = build(weight);
+            graph.train(&[&[1.0]], &[&[0.0]]).unwrap();
+            let output = graph.predict(&[&[1.0]]).unwrap()[0][0];
+            assert!((output - (0.5 - 2.0 * weight)).abs() < 1e-12);
+        }
+    }
+}
@@ -1,20 +1,33 @@
 use crate::activation::ActivationFunction;
+use crate::float::Float;
 use crate::matrix::Matrix;
+use crate::regularization::Regularizer;
 use std::sync::Arc;
 
-pub struct Layer {
+pub struct Layer<T: Float = f64> {
     pub output_size: usize,
-    pub weights: Matrix,
-    pub biases: Matrix,
-    activation: Arc<dyn ActivationFunction>,
-    last_input: Option<Matrix>,
-    last_activation: Option<Matrix>,
+    pub weights: Matrix<T>,
+    pub biases: Matrix<T>,
+    pub kernel_regularizer: Option<Regularizer>,
+    // Biases are left unregularized unless this is set explicitly.
+    pub bias_regularizer: Option<Regularizer>,
+    activation: Arc<dyn ActivationFunction<T>>,
+    // Buffers reused from step to step. They only reallocate when the batch
+    // size grows, so steady-state training does not touch the heap.
+    last_input: Option<Matrix<T>>,
+    last_activation: Option<Matrix<T>>,
+    delta: Matrix<T>,
+    input_error: Matrix<T>,
+    weight_gradient: Matrix<T>,
+    bias_gradient: Matrix<T>,
+    // Set by `backward`, cleared once `apply_gradients` has used the gradients.
+    gradients_pending: bool,
 }
 
-impl Layer {
-    pub fn new(input_size: usize, output_size: usize, activation: Arc<dyn ActivationFunction>) -> Self {
-        let mut weights = Matrix::new(output_size, input_size);
-        let mut biases = Matrix::new(output_size, 1);
+impl<T: Float> Layer<T> {
+    pub fn new(input_size: usize, output_size: usize, activation: Arc<dyn ActivationFunction<T>>) -> Self {
+        let mut weights = Matrix::zeros(output_size, input_size);
+        let mut biases = Matrix::zeros(output_size, 1);
         weights.randomize();
         biases.randomize();
 
@@ -22,75 +35,182 @@ impl Layer {
             output_size,
             weights,
             biases,
+            kernel_regularizer: None,
+            bias_regularizer: None,
             activation,
             last_input: None,
             last_activation: None,
+            delta: Matrix::zeros(output_size, 1),
+            input_error: Matrix::zeros(input_size, 1),
+            weight_gradient: Matrix::zeros(output_size, input_size),
+            bias_gradient: Matrix::zeros(output_size, 1),
+            gradients_pending: false,
         }
     }
 
-    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
-        
-        self.last_input = Some(input.clone());
-        
-        
-        
-        let z = Matrix::dot(&self.weights, input)?.add(&self.biases)?;
-        
-        
-        
-        let activation_output = z.map(|x| self.activation.activate(x));
-        
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix<T>> {
+        vec![&mut self.weights, &mut self.biases]
+    }
+
+    pub fn regularization_penalty(&self) -> f64 {
+        let kernel = self.kernel_regularizer.map_or(0.0, |r| r.penalty(&self.weights.data));
+        let bias = self.bias_regularizer.map_or(0.0, |r| r.penalty(&self.biases.data));
+        kernel + bias
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix<T>) -> Result<Matrix<T>, &'static str> {
+        self.forward(input).cloned()
+    }
+
+    // Forward pass over `input_size x batch` into the layer's own activation
+    // buffer, which is returned by reference instead of copied out.
+    pub fn forward(&mut self, input: &Matrix<T>) -> Result<&Matrix<T>, &'static str> {
+        let output = self.last_activation.get_or_insert_with(|| Matrix::zeros(0, 0));
+        Matrix::dot_into(&self.weights, input, output)?;
+        output.add_column_assign(&self.biases)?;
+        let activation = &self.activation;
+        output.apply_chunks(|chunk| activation.activate_in_place(chunk));
         
-        self.last_activation = Some(activation_output.clone());
+        self.last_input.get_or_insert_with(|| Matrix::zeros(0, 0)).copy_from(input);
         
-        Ok(activation_output)
+        Ok(output)
+    }
+
+    pub fn output(&self) -> Option<&Matrix<T>> {
+        self.last_activation.as_ref()
+    }
+
+    // Stateless forward pass over a batch laid out one sample per column.
+    // Nothing is cached, so it can run from shared references.
+    pub fn infer(&self, input: &Matrix<T>) -> Result<Matrix<T>, &'static str> {
+        let mut z = Matrix::dot(&self.weights, input)?;
+        z.add_column_assign(&self.biases)?;
+        z.apply_chunks(|chunk| self.activation.activate_in_place(chunk));
+        Ok(z)
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix<T>, learning_rate: f64) -> Result<Matrix<T>, &'static str> {
+        let input_error = self.compute_gradients(output_error)?;
+        self.apply_gradients(learning_rate);
+        Ok(input_error)
+    }
+
+    // Computes and stores this layer's update direction (the negative gradient,
+    // including any regularization) without touching the weights, and returns
+    // the error for the previous layer.
+    pub fn compute_gradients(&mut self, output_error: &Matrix<T>) -> Result<Matrix<T>, &'static str> {
+        self.backward(output_error).cloned()
     }
 
-    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+    // `compute_gradients` into the layer's buffers; the returned error for the
+    // previous layer borrows the layer's own storage.
+    pub fn backward(&mut self, output_error: &Matrix<T>) -> Result<&Matrix<T>, &'static str> {
         let last_input = self.last_input.as_ref().ok_or("No input stored for backpropagation")?;
         let last_activation = self.last_activation.as_ref().ok_or("No activation stored for backpropagation")?;
         
         
-        let activation_derivative = last_activation.map(|x| self.activation.derivative(x));
-        
+        let activation = &self.activation;
+        self.delta.copy_from(output_error);
+        self.delta.zip_in_place(last_activation, |error, y| error * activation.derivative(y))?;
         
-        let delta = Matrix::hadamard(output_error, &activation_derivative)?;
-        
-        
-        let input_transpose = Matrix::transpose(last_input);
-        let weight_gradient = Matrix::dot(&delta, &input_transpose)?;
-        
-        
-        let weight_delta = weight_gradient.multiply(learning_rate);
-        let bias_delta = delta.multiply(learning_rate);
         
+        Matrix::dot_nt_into(&self.delta, last_input, &mut self.weight_gradient)?;
+        self.bias_gradient.resize(self.delta.rows, 1);
+        for (g, row) in self.bias_gradient.data.iter_mut().zip(self.delta.data.chunks(self.delta.cols)) {
+            *g = row.iter().copied().sum();
+        }
         
-        let w_rows = self.weights.rows;
-        let w_cols = self.weights.cols;
+        self.add_regularization();
+        self.gradients_pending = true;
         
         
-        for i in 0..w_rows {
-            for j in 0..w_cols {
-                let idx = i * w_cols + j;
-                self.weights.data[idx] += weight_delta.data[idx];
+        Matrix::dot_tn_into(&self.weights, &self.delta, &mut self.input_error)?;
+        Ok(&self.input_error)
+    }
+
+    fn add_regularization(&mut self) {
+        if let Some(regularizer) = self.kernel_regularizer {
+            for (g, &w) in self.weight_gradient.data.iter_mut().zip(&self.weights.data) {
+                *g -= T::from_f64(regularizer.gradient(w.to_f64()));
             }
         }
-        
-        
-        for i in 0..self.biases.rows {
-            self.biases.data[i] += bias_delta.data[i];
+        if let Some(regularizer) = self.bias_regularizer {
+            for (g, &b) in self.bias_gradient.data.iter_mut().zip(&self.biases.data) {
+                *g -= T::from_f64(regularizer.gradient(b.to_f64()));
+            }
+        }
+    }
+
+    // A copy with the same weights and activation for computing gradients on
+    // another thread. It has no regularizers, so its gradients are the data
+    // term alone; see `average_gradients`.
+    pub(crate) fn replica(&self) -> Self {
+        let mut replica = Layer::new(self.weights.cols, self.output_size, Arc::clone(&self.activation));
+        replica.sync_from(self);
+        replica
+    }
+
+    pub(crate) fn sync_from(&mut self, other: &Self) {
+        self.weights.copy_from(&other.weights);
+        self.biases.copy_from(&other.biases);
+    }
+
+    // Sets the pending gradients to the replicas' summed gradients divided by
+    // `samples`, plus this layer's regularization, as if one `backward` had
+    // averaged over the whole batch.
+    pub(crate) fn average_gradients<'a, I>(&mut self, replicas: I, samples: usize) -> Result<(), &'static str>
+    where
+        I: IntoIterator<Item = &'a Layer<T>>,
+    {
+        self.weight_gradient.resize(self.weights.rows, self.weights.cols);
+        self.bias_gradient.resize(self.biases.rows, self.biases.cols);
+        for replica in replicas {
+            self.weight_gradient.add_assign(&replica.weight_gradient)?;
+            self.bias_gradient.add_assign(&replica.bias_gradient)?;
         }
         
+        let scale = T::ONE / T::from_f64(samples as f64);
+        self.weight_gradient.scale_mut(scale);
+        self.bias_gradient.scale_mut(scale);
+        self.add_regularization();
+        self.gradients_pending = true;
+        Ok(())
+    }
+
+    // The error most recently returned by `backward`.
+    pub fn input_error(&self) -> &Matrix<T> {
+        &self.input_error
+    }
+
+    pub fn gradients_mut(&mut self) -> Option<(&mut Matrix<T>, &mut Matrix<T>)> {
+        if self.gradients_pending {
+            Some((&mut self.weight_gradient, &mut self.bias_gradient))
+        } else {
+            None
+        }
+    }
+
+    pub fn apply_gradients(&mut self, learning_rate: f64) {
+        if !self.gradients_pending {
+            return;
+        }
         
-        let weights_transpose = Matrix::transpose(&self.weights);
-        Matrix::dot(&weights_transpose, &delta)
+        let learning_rate = T::from_f64(learning_rate);
+        for (w, g) in self.weights.data.iter_mut().zip(&self.weight_gradient.data) {
+            *w += learning_rate * *g;
+        }
+        for (b, g) in self.biases.data.iter_mut().zip(&self.bias_gradient.data) {
+            *b += learning_rate * *g;
+        }
+        self.gradients_pending = false;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::activation::{ReLU, Sigmoid};
+    use crate::activation::{Linear, ReLU, Sigmoid};
+    use crate::gradcheck;
 
     #[test]
     fn test_layer_creation() {
@@ -113,4 +233,37 @@ mod tests {
         
         assert!((output.get(0, 0) - 0.7310585786300049).abs() < 1e-10);
     }
+
+    #[test]
+    fn test_regularization() {
+        let mut layer = Layer::new(2, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>);
+        layer.weights.data = vec![0.5, -1.0];
+        layer.biases.data = vec![2.0];
+        assert_eq!(layer.regularization_penalty(), 0.0);
+
+        layer.kernel_regularizer = Some(Regularizer::L2(0.1));
+        assert!((layer.regularization_penalty() - 0.125).abs() < 1e-12);
+
+        // With a zero error only the decay term moves the weights, and the bias stays put
+        layer.feed_forward(&Matrix::from_array(&[1.0, 1.0])).unwrap();
+        layer.backpropagate(&Matrix::from_array(&[0.0]), 1.0).unwrap();
+        assert!((layer.weights.get(0, 0) - 0.4).abs() < 1e-12);
+        assert!((layer.weights.get(0, 1) + 0.8).abs() < 1e-12);
+        assert_eq!(layer.biases.get(0, 0), 2.0);
+
+        layer.bias_regularizer = Some(Regularizer::L1(0.5));
+        layer.feed_forward(&Matrix::from_array(&[1.0, 1.0])).unwrap();
+        layer.backpropagate(&Matrix::from_array(&[0.0]), 1.0).unwrap();
+        assert!((layer.biases.get(0, 0) - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_gradients_match_finite_difference() {
+        let mut layer = Layer::new(3, 2, Arc::new(Sigmoid));
+        let input = Matrix::from_array(&[0.4, -1.1, 0.25]);
+
+        assert!(gradcheck::check_module(&mut layer, &input).unwrap().passed(1e-6));
+        assert!(gradcheck::check_module_parameters(&mut layer, &input, |l| &mut l.weights).unwrap().passed(1e-5));
+        assert!(gradcheck::check_module_parameters(&mut layer, &input, |l| &mut l.biases).unwrap().passed(1e-5));
+    }
 }
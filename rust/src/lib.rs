@@ -1,9 +1,51 @@
 pub mod activation;
+pub mod attention;
+pub mod autodiff;
+pub mod checkpoint;
+pub mod clipping;
+pub mod conv1d;
+pub mod divergence;
+pub mod embedding;
+pub mod execution;
+pub mod float;
+mod gemm;
+pub mod gradcheck;
+pub mod graph;
 pub mod layer;
+pub mod loss;
 pub mod matrix;
+pub mod metrics;
+pub mod module;
 pub mod neural_network;
+pub mod normalization;
+pub mod parallel;
+pub mod pooling;
+pub mod recurrent;
+pub mod regularization;
+mod simd;
 
-pub use activation::{ActivationFunction, ReLU, Sigmoid, Softmax};
+pub use activation::{ActivationFunction, Linear, ReLU, Sigmoid, Softmax, Tanh};
+pub use attention::{
+    causal_mask, positional_encoding, scaled_dot_product_attention, MultiHeadAttention, TransformerEncoderBlock,
+};
+pub use autodiff::{AutogradLoss, AutogradModule, Gradients, Tape, Var};
+pub use checkpoint::{Checkpoint, CheckpointPolicy, Checkpointing};
+pub use clipping::GradientClipping;
+pub use conv1d::{Conv1D, Padding};
+pub use divergence::{Divergence, DivergencePolicy, NonFiniteSource, TrainingError};
+pub use embedding::Embedding;
+pub use execution::{ExecutionContext, Thresholds};
+pub use float::Float;
+pub use gradcheck::GradCheck;
+pub use graph::{EpochReport, Graph, Head, HeadMetrics, Merge, NodeId};
 pub use layer::Layer;
+pub use loss::{BinaryCrossEntropy, CategoricalCrossEntropy, LossFunction, MeanAbsoluteError, MeanSquaredError};
 pub use matrix::Matrix;
-pub use neural_network::NeuralNetwork;
+pub use metrics::{Average, Metric};
+pub use module::Module;
+pub use neural_network::{EvaluationReport, NeuralNetwork};
+pub use normalization::LayerNorm;
+pub use parallel::TrainingMode;
+pub use pooling::{AvgPool2D, Flatten, GlobalAveragePool, MaxPool2D};
+pub use recurrent::{GRU, LSTM, Recurrent, RecurrentCell, SimpleRNN};
+pub use regularization::Regularizer;
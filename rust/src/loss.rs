@@ -0,0 +1,146 @@
+pub trait LossFunction: Send + Sync {
+    fn loss(&self, output: &[f64], target: &[f64]) -> f64;
+
+    // Gradient of `loss` with respect to each output.
+    fn gradient(&self, output: &[f64], target: &[f64]) -> Vec<f64>;
+
+    // `gradient` written into a buffer of the output's length. Training calls
+    // this every sample; the default goes through `gradient` and allocates, so
+    // losses should override it to keep training steps allocation-free.
+    fn gradient_into(&self, output: &[f64], target: &[f64], gradient: &mut [f64]) {
+        gradient.copy_from_slice(&self.gradient(output, target));
+    }
+}
+
+const EPSILON: f64 = 1e-12;
+
+pub struct MeanSquaredError;
+
+impl LossFunction for MeanSquaredError {
+    fn loss(&self, output: &[f64], target: &[f64]) -> f64 {
+        let sum: f64 = output.iter().zip(target).map(|(y, t)| (y - t) * (y - t)).sum();
+        sum / output.len() as f64
+    }
+
+    fn gradient(&self, output: &[f64], target: &[f64]) -> Vec<f64> {
+        let mut gradient = vec![0.0; output.len()];
+        self.gradient_into(output, target, &mut gradient);
+        gradient
+    }
+
+    fn gradient_into(&self, output: &[f64], target: &[f64], gradient: &mut [f64]) {
+        let n = output.len() as f64;
+        for ((g, y), t) in gradient.iter_mut().zip(output).zip(target) {
+            *g = 2.0 * (y - t) / n;
+        }
+    }
+}
+
+pub struct MeanAbsoluteError;
+
+impl LossFunction for MeanAbsoluteError {
+    fn loss(&self, output: &[f64], target: &[f64]) -> f64 {
+        let sum: f64 = output.iter().zip(target).map(|(y, t)| (y - t).abs()).sum();
+        sum / output.len() as f64
+    }
+
+    fn gradient(&self, output: &[f64], target: &[f64]) -> Vec<f64> {
+        let mut gradient = vec![0.0; output.len()];
+        self.gradient_into(output, target, &mut gradient);
+        gradient
+    }
+
+    fn gradient_into(&self, output: &[f64], target: &[f64], gradient: &mut [f64]) {
+        let n = output.len() as f64;
+        for ((g, y), t) in gradient.iter_mut().zip(output).zip(target) {
+            *g = if y > t { 1.0 / n } else if y < t { -1.0 / n } else { 0.0 };
+        }
+    }
+}
+
+// Expects outputs in (0, 1), e.g. from a sigmoid layer.
+pub struct BinaryCrossEntropy;
+
+impl LossFunction for BinaryCrossEntropy {
+    fn loss(&self, output: &[f64], target: &[f64]) -> f64 {
+        let sum: f64 = output.iter().zip(target)
+            .map(|(&y, &t)| {
+                let y = y.clamp(EPSILON, 1.0 - EPSILON);
+                -(t * y.ln() + (1.0 - t) * (1.0 - y).ln())
+            })
+            .sum();
+        sum / output.len() as f64
+    }
+
+    fn gradient(&self, output: &[f64], target: &[f64]) -> Vec<f64> {
+        let mut gradient = vec![0.0; output.len()];
+        self.gradient_into(output, target, &mut gradient);
+        gradient
+    }
+
+    fn gradient_into(&self, output: &[f64], target: &[f64], gradient: &mut [f64]) {
+        let n = output.len() as f64;
+        for ((g, &y), &t) in gradient.iter_mut().zip(output).zip(target) {
+            let y = y.clamp(EPSILON, 1.0 - EPSILON);
+            *g = (y - t) / (y * (1.0 - y)) / n;
+        }
+    }
+}
+
+// Expects a probability distribution as output and a one-hot (or soft) target.
+pub struct CategoricalCrossEntropy;
+
+impl LossFunction for CategoricalCrossEntropy {
+    fn loss(&self, output: &[f64], target: &[f64]) -> f64 {
+        output.iter().zip(target)
+            .map(|(&y, &t)| -t * y.max(EPSILON).ln())
+            .sum()
+    }
+
+    fn gradient(&self, output: &[f64], target: &[f64]) -> Vec<f64> {
+        let mut gradient = vec![0.0; output.len()];
+        self.gradient_into(output, target, &mut gradient);
+        gradient
+    }
+
+    fn gradient_into(&self, output: &[f64], target: &[f64], gradient: &mut [f64]) {
+        for ((g, &y), &t) in gradient.iter_mut().zip(output).zip(target) {
+            *g = -t / y.max(EPSILON);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck;
+
+    fn check_gradient(loss: &dyn LossFunction, output: &[f64], target: &[f64]) {
+        assert!(gradcheck::check_loss(loss, output, target).passed(1e-6));
+    }
+
+    #[test]
+    fn test_mean_squared_error() {
+        let mse = MeanSquaredError;
+        assert!((mse.loss(&[1.0, 2.0], &[0.0, 4.0]) - 2.5).abs() < 1e-12);
+        check_gradient(&mse, &[0.3, -0.2, 0.9], &[0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_mean_absolute_error() {
+        let mae = MeanAbsoluteError;
+        assert!((mae.loss(&[1.0, 2.0], &[0.0, 4.0]) - 1.5).abs() < 1e-12);
+        check_gradient(&mae, &[0.3, -0.2, 0.9], &[0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let bce = BinaryCrossEntropy;
+        assert!((bce.loss(&[0.5], &[1.0]) - 2f64.ln()).abs() < 1e-12);
+        check_gradient(&bce, &[0.3, 0.8, 0.6], &[0.0, 1.0, 1.0]);
+
+        let cce = CategoricalCrossEntropy;
+        assert!((cce.loss(&[0.25, 0.75], &[0.0, 1.0]) + 0.75f64.ln()).abs() < 1e-12);
+        check_gradient(&cce, &[0.2, 0.5, 0.3], &[0.0, 1.0, 0.0]);
+    }
+}
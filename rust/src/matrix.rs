@@ -1,44 +1,65 @@
+use crate::execution;
+use crate::float::Float;
+use crate::gemm::{self, View};
+use crate::simd::{self, Binary, Unary};
 use rand::Rng;
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
 use std::fmt;
 use rayon::prelude::*;
 
-#[derive(Clone, Debug)]
-pub struct Matrix {
+// Elements per task when element-wise work is split across threads.
+const PARALLEL_CHUNK: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<T = f64> {
     pub rows: usize,
     pub cols: usize,
-    pub data: Vec<f64>,
+    pub data: Vec<T>,
 }
 
+// `new` only exists for the f64 default so `Matrix::new(r, c)` keeps inferring
+// its element type; other types use `Matrix::<f32>::zeros`.
 impl Matrix {
     pub fn new(rows: usize, cols: usize) -> Self {
-        let data = vec![0.0; rows * cols];
+        Matrix::zeros(rows, cols)
+    }
+}
+
+impl<T: Float> Matrix<T> {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        let data = vec![T::ZERO; rows * cols];
         Matrix { rows, cols, data }
     }
 
-    pub fn from_array(array: &[f64]) -> Self {
+    pub fn from_array(array: &[T]) -> Self {
         let rows = array.len();
         let cols = 1;
-        let mut data = vec![0.0; rows * cols];
+        let mut data = vec![T::ZERO; rows * cols];
         data.copy_from_slice(array);
         Matrix { rows, cols, data }
     }
 
-    pub fn to_array(&self) -> Vec<f64> {
+    pub fn to_array(&self) -> Vec<T> {
         self.data.clone()
     }
 
+    // Converts to another element type, e.g. to store f32 weights as f64.
+    pub fn cast<U: Float>(&self) -> Matrix<U> {
+        let data = self.data.iter().map(|&v| U::from_f64(v.to_f64())).collect();
+        Matrix { rows: self.rows, cols: self.cols, data }
+    }
+
     pub fn randomize(&mut self) {
         // Use a thread-safe RNG for parallel code
         let seed = rand::random::<u64>();
         
         // Create a new vector with random values
-        let random_data: Vec<f64> = (0..self.data.len())
+        let random_data: Vec<T> = (0..self.data.len())
             .into_par_iter()
             .map(|_| {
                 let mut local_rng = SmallRng::seed_from_u64(seed.wrapping_add(rand::random::<u64>()));
-                local_rng.gen::<f64>() * 2.0 - 1.0
+                T::from_f64(local_rng.gen::<f64>() * 2.0 - 1.0)
             })
             .collect();
         
@@ -46,134 +67,194 @@ impl Matrix {
         self.data = random_data;
     }
 
-    pub fn dot(a: &Matrix, b: &Matrix) -> Result<Matrix, &'static str> {
+    pub fn dot(a: &Self, b: &Self) -> Result<Self, &'static str> {
         if a.cols != b.rows {
             return Err("Incompatible matrix sizes for dot product");
         }
-        
-        let mut result = Matrix::new(a.rows, b.cols);
-        
-        // Lower threshold for parallelization in tests
-        // This makes small matrix operations faster in tests
-        let threshold = if cfg!(test) { 100 } else { 1000 };
-        
-        // Parallel execution for larger matrices, sequential for smaller ones
-        if a.rows * b.cols > threshold {
-            // Create a copy of the data for thread safety
-            let a_data = a.data.clone();
-            let b_data = b.data.clone();
-            let a_cols = a.cols;
-            let b_cols = b.cols;
-            
-            // Use direct indexing for better performance
-            let result_data = (0..a.rows)
-                .into_par_iter()
-                .flat_map(|i| {
-                    let mut row_result = vec![0.0; b_cols];
-                    let i_offset = i * a_cols;
-                    
-                    for j in 0..b_cols {
-                        let mut sum = 0.0;
-                        for k in 0..a_cols {
-                            sum += a_data[i_offset + k] * b_data[k * b_cols + j];
-                        }
-                        row_result[j] = sum;
-                    }
-                    
-                    row_result
-                })
-                .collect();
-            
-            // Replace the entire data vector at once
-            result.data = result_data;
-        } else {
-            // For small matrices, use direct indexing for better performance
-            let a_data = &a.data;
-            let b_data = &b.data;
-            let a_cols = a.cols;
-            let b_cols = b.cols;
-            
-            for i in 0..a.rows {
-                for j in 0..b.cols {
-                    let mut sum = 0.0;
-                    let i_offset = i * a_cols;
-                    
-                    for k in 0..a_cols {
-                        sum += a_data[i_offset + k] * b_data[k * b_cols + j];
-                    }
-                    
-                    result.data[i * b_cols + j] = sum;
-                }
-            }
+        Ok(Matrix::product(a.rows, b.cols, a.cols, View::new(&a.data, a.cols), View::new(&b.data, b.cols)))
+    }
+
+    // a^T * b, reading `a` transposed in place instead of copying it.
+    pub fn dot_tn(a: &Self, b: &Self) -> Result<Self, &'static str> {
+        if a.rows != b.rows {
+            return Err("Incompatible matrix sizes for dot product");
         }
-        
+        Ok(Matrix::product(a.cols, b.cols, a.rows, View::transposed(&a.data, a.cols), View::new(&b.data, b.cols)))
+    }
+
+    // a * b^T, reading `b` transposed in place instead of copying it.
+    pub fn dot_nt(a: &Self, b: &Self) -> Result<Self, &'static str> {
+        if a.cols != b.cols {
+            return Err("Incompatible matrix sizes for dot product");
+        }
+        Ok(Matrix::product(a.rows, b.rows, a.cols, View::new(&a.data, a.cols), View::transposed(&b.data, b.cols)))
+    }
+
+    fn product(rows: usize, cols: usize, inner: usize, a: View<T>, b: View<T>) -> Self {
+        let mut result = Matrix::zeros(rows, cols);
+        result.product_into(inner, a, b);
+        result
+    }
+
+    // Accumulates `a * b` into `self`, whose shape is already the product's.
+    fn product_into(&mut self, inner: usize, a: View<T>, b: View<T>) {
+        let parallel = self.rows * self.cols > execution::thresholds().product;
+        gemm::gemm(self.rows, self.cols, inner, a, b, &mut self.data, parallel);
+    }
+
+    pub fn add(&self, other: &Self) -> Result<Self, &'static str> {
+        let mut result = self.clone();
+        result.add_assign(other)?;
         Ok(result)
     }
 
-    pub fn add(&self, other: &Matrix) -> Result<Matrix, &'static str> {
-        self.check_size_match(other)?;
-        let mut result = Matrix::new(self.rows, self.cols);
-        
-        // Create a parallel iterator to process the data
-        let self_data = &self.data;
-        let other_data = &other.data;
-        
-        let processed: Vec<f64> = (0..self.data.len())
-            .into_par_iter()
-            .map(|i| self_data[i] + other_data[i])
-            .collect();
-        
-        result.data = processed;
+    pub fn subtract(&self, other: &Self) -> Result<Self, &'static str> {
+        let mut result = self.clone();
+        result.sub_assign(other)?;
         Ok(result)
     }
 
-    pub fn subtract(&self, other: &Matrix) -> Result<Matrix, &'static str> {
+    pub fn multiply(&self, scalar: T) -> Self {
+        let mut result = self.clone();
+        result.scale_mut(scalar);
+        result
+    }
+
+    pub fn add_assign(&mut self, other: &Self) -> Result<(), &'static str> {
+        self.zip_simd(other, Binary::Add)
+    }
+
+    pub fn sub_assign(&mut self, other: &Self) -> Result<(), &'static str> {
+        self.zip_simd(other, Binary::Sub)
+    }
+
+    pub fn hadamard_assign(&mut self, other: &Self) -> Result<(), &'static str> {
+        self.zip_simd(other, Binary::Mul)
+    }
+
+    pub fn scale_mut(&mut self, scalar: T) {
+        self.apply_chunks(|chunk| simd::unary(Unary::Scale(scalar), chunk));
+    }
+
+    // Vectorized approximation; see `simd` for the accuracy.
+    pub fn exp_in_place(&mut self) {
+        self.apply_chunks(|chunk| simd::unary(Unary::Exp, chunk));
+    }
+
+    // Natural logarithm.
+    pub fn ln_in_place(&mut self) {
+        self.apply_chunks(|chunk| chunk.iter_mut().for_each(|v| *v = v.ln()));
+    }
+
+    pub fn sqrt_in_place(&mut self) {
+        self.apply_chunks(|chunk| chunk.iter_mut().for_each(|v| *v = v.sqrt()));
+    }
+
+    pub fn powf_in_place(&mut self, exponent: T) {
+        self.apply_chunks(|chunk| chunk.iter_mut().for_each(|v| *v = v.powf(exponent)));
+    }
+
+    // Bounds must be ordered and not NaN.
+    pub fn clamp_in_place(&mut self, min: T, max: T) -> Result<(), &'static str> {
+        // NaN bounds are unordered
+        if min.partial_cmp(&max).is_none_or(|order| order.is_gt()) {
+            return Err("Clamp bounds must be ordered and not NaN");
+        }
+        self.apply_chunks(|chunk| chunk.iter_mut().for_each(|v| *v = v.clamp(min, max)));
+        Ok(())
+    }
+
+    fn zip_simd(&mut self, other: &Self, op: Binary) -> Result<(), &'static str> {
         self.check_size_match(other)?;
-        let mut result = Matrix::new(self.rows, self.cols);
         
-        // Create a parallel iterator to process the data
-        let self_data = &self.data;
-        let other_data = &other.data;
-        
-        let processed: Vec<f64> = (0..self.data.len())
-            .into_par_iter()
-            .map(|i| self_data[i] - other_data[i])
-            .collect();
-        
-        result.data = processed;
-        Ok(result)
+        if self.data.len() > execution::thresholds().elementwise {
+            self.data.par_chunks_mut(PARALLEL_CHUNK)
+                .zip(other.data.par_chunks(PARALLEL_CHUNK))
+                .for_each(|(x, y)| simd::binary(op, x, y));
+        } else {
+            simd::binary(op, &mut self.data, &other.data);
+        }
+        Ok(())
     }
 
-    pub fn multiply(&self, scalar: f64) -> Matrix {
-        let mut result = Matrix::new(self.rows, self.cols);
-        
-        // Create a parallel iterator to process the data
-        let self_data = &self.data;
-        
-        let processed: Vec<f64> = self_data
-            .par_iter()
-            .map(|&val| val * scalar)
-            .collect();
-        
-        result.data = processed;
-        result
+    // Writes `a * b` into `out`, reshaping it as needed. `out`'s buffer is
+    // reused, so nothing is allocated once it has grown to the largest product.
+    pub fn dot_into(a: &Self, b: &Self, out: &mut Self) -> Result<(), &'static str> {
+        if a.cols != b.rows {
+            return Err("Incompatible matrix sizes for dot product");
+        }
+        out.resize(a.rows, b.cols);
+        out.product_into(a.cols, View::new(&a.data, a.cols), View::new(&b.data, b.cols));
+        Ok(())
     }
 
-    pub fn transpose(m: &Matrix) -> Matrix {
-        let mut result = Matrix::new(m.cols, m.rows);
+    // `a^T * b` into `out`; see `dot_into`.
+    pub fn dot_tn_into(a: &Self, b: &Self, out: &mut Self) -> Result<(), &'static str> {
+        if a.rows != b.rows {
+            return Err("Incompatible matrix sizes for dot product");
+        }
+        out.resize(a.cols, b.cols);
+        out.product_into(a.rows, View::transposed(&a.data, a.cols), View::new(&b.data, b.cols));
+        Ok(())
+    }
+
+    // `a * b^T` into `out`; see `dot_into`.
+    pub fn dot_nt_into(a: &Self, b: &Self, out: &mut Self) -> Result<(), &'static str> {
+        if a.cols != b.cols {
+            return Err("Incompatible matrix sizes for dot product");
+        }
+        out.resize(a.rows, b.rows);
+        out.product_into(a.cols, View::new(&a.data, a.cols), View::transposed(&b.data, b.cols));
+        Ok(())
+    }
+
+    // Reshapes to `rows x cols` of zeros, keeping the existing allocation when
+    // it is already large enough.
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.rows = rows;
+        self.cols = cols;
+        self.data.clear();
+        self.data.resize(rows * cols, T::ZERO);
+    }
+
+    // Copies `other` into `self` without allocating when the buffer is large enough.
+    pub fn copy_from(&mut self, other: &Self) {
+        self.rows = other.rows;
+        self.cols = other.cols;
+        self.data.clear();
+        self.data.extend_from_slice(&other.data);
+    }
+
+    pub(crate) fn zip_in_place<F>(&mut self, other: &Self, func: F) -> Result<(), &'static str>
+    where
+        F: Fn(T, T) -> T + Send + Sync,
+    {
+        self.check_size_match(other)?;
         
-        // Lower threshold for parallelization in tests
-        let threshold = if cfg!(test) { 100 } else { 1000 };
+        if self.data.len() > execution::thresholds().elementwise {
+            self.data.par_iter_mut()
+                .zip(other.data.par_iter())
+                .for_each(|(x, &y)| *x = func(*x, y));
+        } else {
+            for (x, &y) in self.data.iter_mut().zip(&other.data) {
+                *x = func(*x, y);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn transpose(m: &Self) -> Self {
+        let mut result = Matrix::zeros(m.cols, m.rows);
         
         // Use parallel execution for larger matrices
-        if m.rows * m.cols > threshold {
+        if m.rows * m.cols > execution::thresholds().transpose {
             // Direct indexing for better performance
             let m_data = &m.data;
             let m_cols = m.cols;
             let result_cols = result.cols;
             
             // Create transposed data in parallel and then assign it
-            let transposed_data: Vec<(usize, f64)> = (0..m.rows)
+            let transposed_data: Vec<(usize, T)> = (0..m.rows)
                 .into_par_iter()
                 .flat_map(|i| {
                     let mut row_data = Vec::with_capacity(m.cols);
@@ -206,108 +287,349 @@ impl Matrix {
         result
     }
 
-    pub fn map<F>(&self, func: F) -> Matrix
+    pub fn map<F>(&self, func: F) -> Self
     where
-        F: Fn(f64) -> f64 + Send + Sync,
+        F: Fn(T) -> T + Send + Sync,
     {
-        let mut result = Matrix::new(self.rows, self.cols);
-        
-        // Lower threshold for parallelization in tests
-        let threshold = if cfg!(test) { 100 } else { 1000 };
-        
-        // Use parallel execution only for larger matrices
-        if self.data.len() > threshold {
-            // Create a parallel iterator to process the data
-            let processed: Vec<f64> = self.data
-                .par_iter()
-                .map(|&val| func(val))
-                .collect();
-            
-            result.data = processed;
-        } else {
-            // For small matrices, use direct indexing without parallelism
-            for (i, &val) in self.data.iter().enumerate() {
-                result.data[i] = func(val);
-            }
-        }
-        
+        let mut result = self.clone();
+        result.apply_in_place(func);
         result
     }
     
-    pub fn hadamard(a: &Matrix, b: &Matrix) -> Result<Matrix, &'static str> {
-        a.check_size_match(b)?;
-        let mut result = Matrix::new(a.rows, a.cols);
-        
-        // Lower threshold for parallelization in tests
-        let threshold = if cfg!(test) { 100 } else { 1000 };
-        
-        // Use parallel execution only for larger matrices
-        if a.data.len() > threshold {
-            // Create a parallel iterator to process the data
-            let a_data = &a.data;
-            let b_data = &b.data;
-            
-            // Use SIMD-friendly processing in chunks
-            let processed: Vec<f64> = (0..a.data.len())
-                .into_par_iter()
-                .map(|i| a_data[i] * b_data[i])
-                .collect();
-            
-            result.data = processed;
-        } else {
-            // For small matrices, use direct indexing without parallelism
-            for i in 0..a.data.len() {
-                result.data[i] = a.data[i] * b.data[i];
-            }
-        }
-        
+    pub fn hadamard(a: &Self, b: &Self) -> Result<Self, &'static str> {
+        let mut result = a.clone();
+        result.hadamard_assign(b)?;
         Ok(result)
     }
     
-    fn check_size_match(&self, other: &Matrix) -> Result<(), &'static str> {
+    pub(crate) fn check_size_match(&self, other: &Self) -> Result<(), &'static str> {
         if self.rows != other.rows || self.cols != other.cols {
             return Err("Matrix size mismatch");
         }
         Ok(())
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.data.iter().all(|v| v.is_finite())
+    }
+
     #[inline]
-    pub fn get(&self, row: usize, col: usize) -> f64 {
+    pub fn get(&self, row: usize, col: usize) -> T {
         self.data[row * self.cols + col]
     }
 
     #[inline]
-    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+    pub fn set(&mut self, row: usize, col: usize, value: T) {
         self.data[row * self.cols + col] = value;
     }
     
+    // Like `apply_in_place`, but hands `func` whole runs of elements so it can
+    // use vectorized kernels.
+    pub fn apply_chunks<F>(&mut self, func: F)
+    where
+        F: Fn(&mut [T]) + Send + Sync,
+    {
+        if self.data.len() > execution::thresholds().elementwise {
+            self.data.par_chunks_mut(PARALLEL_CHUNK).for_each(func);
+        } else {
+            func(&mut self.data);
+        }
+    }
+    
     pub fn apply_in_place<F>(&mut self, func: F)
     where
-        F: Fn(f64) -> f64 + Send + Sync,
+        F: Fn(T) -> T + Send + Sync,
     {
-        // Lower threshold for parallelization in tests
-        let threshold = if cfg!(test) { 100 } else { 1000 };
-        
         // Use parallel execution only for larger matrices
-        if self.data.len() > threshold {
-            // Create a new vector with processed values
-            let processed: Vec<f64> = self.data
-                .par_iter()
-                .map(|&val| func(val))
-                .collect();
-            
-            // Replace the data with processed values
-            self.data = processed;
+        if self.data.len() > execution::thresholds().elementwise {
+            self.data.par_iter_mut().for_each(|val| *val = func(*val));
         } else {
             // For small matrices, modify in place without parallelism
-            for i in 0..self.data.len() {
-                self.data[i] = func(self.data[i]);
+            for val in &mut self.data {
+                *val = func(*val);
+            }
+        }
+    }
+
+    // Adds the `rows x 1` `column` to every column, e.g. biases across a batch.
+    pub fn add_column_assign(&mut self, column: &Self) -> Result<(), &'static str> {
+        if column.rows != self.rows || column.cols != 1 {
+            return Err("Column vector does not match matrix rows");
+        }
+        if self.cols == 0 {
+            return Ok(());
+        }
+        
+        let add = |row: &mut [T], value: T| row.iter_mut().for_each(|x| *x += value);
+        if self.data.len() > execution::thresholds().elementwise {
+            self.data.par_chunks_mut(self.cols)
+                .zip(column.data.par_iter())
+                .for_each(|(row, &value)| add(row, value));
+        } else {
+            for (row, &value) in self.data.chunks_mut(self.cols).zip(&column.data) {
+                add(row, value);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_column(&self, column: &Self) -> Result<Self, &'static str> {
+        let mut result = self.clone();
+        result.add_column_assign(column)?;
+        Ok(result)
+    }
+
+    // `column` repeated `cols` times.
+    pub fn broadcast_column(column: &Self, cols: usize) -> Result<Self, &'static str> {
+        let mut result = Matrix::zeros(column.rows, cols);
+        result.add_column_assign(column)?;
+        Ok(result)
+    }
+
+    // Sum of each row, as a `rows x 1` column.
+    pub fn row_sums(&self) -> Self {
+        let mut result = Matrix::zeros(self.rows, 1);
+        if self.cols == 0 {
+            return result;
+        }
+        
+        let sum = |row: &[T]| row.iter().copied().sum();
+        if self.data.len() > execution::thresholds().reduction {
+            result.data.par_iter_mut()
+                .zip(self.data.par_chunks(self.cols))
+                .for_each(|(total, row)| *total = sum(row));
+        } else {
+            for (total, row) in result.data.iter_mut().zip(self.data.chunks(self.cols)) {
+                *total = sum(row);
             }
         }
+        result
+    }
+
+    // Sum of each column, as a `1 x cols` row.
+    pub fn col_sums(&self) -> Self {
+        let cols = self.cols;
+        let accumulate = |mut totals: Vec<T>, rows: &[T]| {
+            for row in rows.chunks(cols) {
+                for (total, &value) in totals.iter_mut().zip(row) {
+                    *total += value;
+                }
+            }
+            totals
+        };
+        
+        let data = if cols == 0 {
+            Vec::new()
+        } else if self.data.len() > execution::thresholds().reduction {
+            // Whole rows per task, each summed into its own row of totals
+            let rows_per_task = (PARALLEL_CHUNK / cols).max(1);
+            self.data.par_chunks(rows_per_task * cols)
+                .fold(|| vec![T::ZERO; cols], accumulate)
+                .reduce(|| vec![T::ZERO; cols], |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+                    a
+                })
+        } else {
+            accumulate(vec![T::ZERO; cols], &self.data)
+        };
+        Matrix { rows: 1, cols, data }
+    }
+
+    // The mean of no values is NaN, so a `rows x 0` matrix gives a NaN column.
+    pub fn row_means(&self) -> Self {
+        let mut means = self.row_sums();
+        if self.cols == 0 {
+            means.data.fill(T::NAN);
+        } else {
+            means.scale_mut(T::ONE / T::from_f64(self.cols as f64));
+        }
+        means
+    }
+
+    // NaN for every column of a `0 x cols` matrix, as with `row_means`.
+    pub fn col_means(&self) -> Self {
+        let mut means = self.col_sums();
+        if self.rows == 0 {
+            means.data.fill(T::NAN);
+        } else {
+            means.scale_mut(T::ONE / T::from_f64(self.rows as f64));
+        }
+        means
+    }
+
+    // Each column's largest value and its row, or `None` for a column of only
+    // NaNs. NaNs are skipped, and ties go to the first row.
+    fn col_maxima(&self) -> Result<Vec<Option<(usize, T)>>, &'static str> {
+        if self.rows == 0 {
+            return Err("Matrix has no rows");
+        }
+        
+        let scan = |col: usize| {
+            let mut best: Option<(usize, T)> = None;
+            for row in 0..self.rows {
+                let value = self.data[row * self.cols + col];
+                // NaN fails both comparisons, and anything else is at least -inf
+                if best.map_or(value >= T::NEG_INFINITY, |(_, max)| value > max) {
+                    best = Some((row, value));
+                }
+            }
+            best
+        };
+        if self.data.len() > execution::thresholds().reduction {
+            Ok((0..self.cols).into_par_iter().map(scan).collect())
+        } else {
+            Ok((0..self.cols).map(scan).collect())
+        }
+    }
+
+    // Largest value of each column, as a `1 x cols` row; NaN for a column of
+    // only NaNs.
+    pub fn col_max(&self) -> Result<Self, &'static str> {
+        let data = self.col_maxima()?.into_iter()
+            .map(|best| best.map_or(T::NAN, |(_, value)| value))
+            .collect();
+        Ok(Matrix { rows: 1, cols: self.cols, data })
+    }
+
+    // Row of the largest value in each column. A column of only NaNs has no
+    // such row and is an error.
+    pub fn col_argmax(&self) -> Result<Vec<usize>, &'static str> {
+        self.col_maxima()?.into_iter()
+            .map(|best| best.map(|(row, _)| row).ok_or("Column has no comparable value"))
+            .collect()
+    }
+
+    // Sums `func` over runs of elements, in parallel for large matrices.
+    fn reduce<F>(&self, func: F) -> T
+    where
+        F: Fn(&[T]) -> T + Send + Sync,
+    {
+        if self.data.len() > execution::thresholds().reduction {
+            self.data.par_chunks(PARALLEL_CHUNK).map(func).sum()
+        } else {
+            func(&self.data)
+        }
+    }
+
+    // Norms over all entries: L1 sums the magnitudes, L2 is the Euclidean norm.
+    pub fn l1_norm(&self) -> T {
+        self.reduce(|chunk| chunk.iter().map(|v| v.abs()).sum())
+    }
+
+    pub fn l2_norm(&self) -> T {
+        self.reduce(|chunk| chunk.iter().map(|&v| v * v).sum()).sqrt()
+    }
+
+    // The entry-wise L2 norm, under its usual name for matrices.
+    pub fn frobenius_norm(&self) -> T {
+        self.l2_norm()
     }
 }
 
-impl fmt::Display for Matrix {
+// Operator forms of the methods above. Like indexing out of bounds, mismatched
+// shapes are a programming error here and panic; use the methods to get a Result.
+// `*` between matrices is the matrix product, and with a scalar it scales.
+macro_rules! impl_elementwise_op {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident, $inherent:ident) => {
+        impl<T: Float> std::ops::$assign_op<&Matrix<T>> for Matrix<T> {
+            fn $assign_method(&mut self, rhs: &Matrix<T>) {
+                Matrix::$inherent(self, rhs).unwrap_or_else(|e| panic!("{}", e));
+            }
+        }
+
+        impl<T: Float> std::ops::$assign_op<Matrix<T>> for Matrix<T> {
+            fn $assign_method(&mut self, rhs: Matrix<T>) {
+                std::ops::$assign_op::$assign_method(self, &rhs);
+            }
+        }
+
+        impl<T: Float> std::ops::$op<&Matrix<T>> for Matrix<T> {
+            type Output = Matrix<T>;
+
+            fn $method(mut self, rhs: &Matrix<T>) -> Matrix<T> {
+                std::ops::$assign_op::$assign_method(&mut self, rhs);
+                self
+            }
+        }
+
+        impl<T: Float> std::ops::$op<Matrix<T>> for Matrix<T> {
+            type Output = Matrix<T>;
+
+            fn $method(self, rhs: Matrix<T>) -> Matrix<T> {
+                std::ops::$op::$method(self, &rhs)
+            }
+        }
+
+        impl<T: Float> std::ops::$op<&Matrix<T>> for &Matrix<T> {
+            type Output = Matrix<T>;
+
+            fn $method(self, rhs: &Matrix<T>) -> Matrix<T> {
+                std::ops::$op::$method(self.clone(), rhs)
+            }
+        }
+    };
+}
+
+impl_elementwise_op!(Add, add, AddAssign, add_assign, add_assign);
+impl_elementwise_op!(Sub, sub, SubAssign, sub_assign, sub_assign);
+
+impl<T: Float> std::ops::MulAssign<T> for Matrix<T> {
+    fn mul_assign(&mut self, scalar: T) {
+        self.scale_mut(scalar);
+    }
+}
+
+impl<T: Float> std::ops::Mul<T> for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(mut self, scalar: T) -> Matrix<T> {
+        self.scale_mut(scalar);
+        self
+    }
+}
+
+impl<T: Float> std::ops::Mul<T> for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, scalar: T) -> Matrix<T> {
+        self.multiply(scalar)
+    }
+}
+
+impl<T: Float> std::ops::Mul<&Matrix<T>> for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: &Matrix<T>) -> Matrix<T> {
+        Matrix::dot(self, rhs).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T: Float> std::ops::Mul<Matrix<T>> for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, rhs: Matrix<T>) -> Matrix<T> {
+        &self * &rhs
+    }
+}
+
+impl<T: Float> std::ops::Neg for Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn neg(mut self) -> Matrix<T> {
+        self.scale_mut(-T::ONE);
+        self
+    }
+}
+
+impl<T: Float> std::ops::Neg for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn neg(self) -> Matrix<T> {
+        -self.clone()
+    }
+}
+
+impl<T: Float> fmt::Display for Matrix<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for i in 0..self.rows {
             for j in 0..self.cols {
@@ -378,6 +700,101 @@ mod tests {
         assert_eq!(result.get(1, 1), 154.0);
     }
     
+    #[test]
+    fn test_transposed_products() {
+        let a = Matrix { rows: 3, cols: 2, data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0] };
+        let b = Matrix { rows: 3, cols: 4, data: (0..12).map(|i| i as f64 - 4.0).collect() };
+        let c = Matrix { rows: 4, cols: 2, data: vec![0.5, -1.0, 2.0, 0.0, 1.5, 3.0, -2.0, 1.0] };
+
+        let tn = Matrix::dot_tn(&a, &b).unwrap();
+        assert_eq!((tn.rows, tn.cols), (2, 4));
+        assert_eq!(tn.data, Matrix::dot(&Matrix::transpose(&a), &b).unwrap().data);
+
+        let nt = Matrix::dot_nt(&a, &c).unwrap();
+        assert_eq!((nt.rows, nt.cols), (3, 4));
+        assert_eq!(nt.data, Matrix::dot(&a, &Matrix::transpose(&c)).unwrap().data);
+
+        assert!(Matrix::dot_tn(&a, &c).is_err());
+        assert!(Matrix::dot_nt(&a, &b).is_err());
+    }
+
+    #[test]
+    fn test_in_place_operations() {
+        let mut a = Matrix { rows: 2, cols: 2, data: vec![1.0, 2.0, 3.0, 4.0] };
+        let b = Matrix { rows: 2, cols: 2, data: vec![0.5, -1.0, 2.0, 0.0] };
+        let ptr = a.data.as_ptr();
+
+        a.add_assign(&b).unwrap();
+        assert_eq!(a.data, vec![1.5, 1.0, 5.0, 4.0]);
+        a.hadamard_assign(&b).unwrap();
+        assert_eq!(a.data, vec![0.75, -1.0, 10.0, 0.0]);
+        a.scale_mut(2.0);
+        a.sub_assign(&b).unwrap();
+        assert_eq!(a.data, vec![1.0, -1.0, 18.0, 0.0]);
+        assert_eq!(a.data.as_ptr(), ptr);
+        assert!(a.add_assign(&Matrix::new(1, 2)).is_err());
+
+        // dot_into reuses the output buffer once it is large enough
+        let mut out = Matrix::new(4, 4);
+        let ptr = out.data.as_ptr();
+        Matrix::dot_into(&a, &b, &mut out).unwrap();
+        assert_eq!((out.rows, out.cols), (2, 2));
+        assert_eq!(out.data, Matrix::dot(&a, &b).unwrap().data);
+        assert_eq!(out.data.as_ptr(), ptr);
+
+        Matrix::dot_nt_into(&a, &b, &mut out).unwrap();
+        assert_eq!(out.data, Matrix::dot_nt(&a, &b).unwrap().data);
+        Matrix::dot_tn_into(&a, &b, &mut out).unwrap();
+        assert_eq!(out.data, Matrix::dot_tn(&a, &b).unwrap().data);
+        assert_eq!(out.data.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_operators() {
+        let a = Matrix { rows: 2, cols: 2, data: vec![1.0, 2.0, 3.0, 4.0] };
+        let b = Matrix { rows: 2, cols: 2, data: vec![0.0, 1.0, 1.0, 0.0] };
+
+        assert_eq!((&a + &b).data, vec![1.0, 3.0, 4.0, 4.0]);
+        assert_eq!((&a - &b).data, vec![1.0, 1.0, 2.0, 4.0]);
+        assert_eq!((&a * &b).data, vec![2.0, 1.0, 4.0, 3.0]);
+        assert_eq!((&a * 2.0).data, vec![2.0, 4.0, 6.0, 8.0]);
+        assert_eq!((-&a).data, vec![-1.0, -2.0, -3.0, -4.0]);
+        assert_eq!((a.clone() + b.clone() - &b).data, a.data);
+
+        let mut c = a.clone();
+        c += &b;
+        c -= a.clone();
+        c *= 3.0;
+        assert_eq!(c.data, vec![0.0, 3.0, 3.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix size mismatch")]
+    fn test_operator_shape_mismatch_panics() {
+        let _ = &Matrix::new(2, 2) + &Matrix::new(2, 3);
+    }
+
+    #[test]
+    fn test_f32_matches_f64() {
+        // Large enough to go through the packed kernel
+        let mut a = Matrix::new(40, 50);
+        let mut b = Matrix::new(50, 30);
+        a.randomize();
+        b.randomize();
+        let (a32, b32): (Matrix<f32>, Matrix<f32>) = (a.cast(), b.cast());
+
+        let expected = Matrix::dot(&a, &b).unwrap();
+        let actual = Matrix::dot(&a32, &b32).unwrap().cast::<f64>();
+        for (x, y) in actual.data.iter().zip(&expected.data) {
+            assert!((x - y).abs() < 1e-4);
+        }
+
+        let mut c = Matrix::<f32>::zeros(2, 2);
+        c.data = vec![1.0, -2.0, 3.0, 0.5];
+        assert_eq!((&c * 2.0f32 - &c).data, c.data);
+        assert_eq!(c.cast::<f64>().cast::<f32>(), c);
+    }
+
     #[test]
     fn test_parallel_operations() {
         let size = 100;
@@ -404,4 +821,94 @@ mod tests {
         let mapped = a.map(|x| x * 10.0);
         assert_eq!(mapped.get(50, 50), 10.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_reductions() {
+        let mut m = Matrix::new(2, 3);
+        m.data = vec![1.0, -4.0, 2.0, 3.0, 0.5, -2.0];
+        
+        assert_eq!(m.row_sums().data, vec![-1.0, 1.5]);
+        assert_eq!(m.col_sums().data, vec![4.0, -3.5, 0.0]);
+        assert_eq!(m.row_means().data, vec![-1.0 / 3.0, 0.5]);
+        assert_eq!(m.col_means().data, vec![2.0, -1.75, 0.0]);
+        assert_eq!((m.col_sums().rows, m.col_sums().cols), (1, 3));
+        
+        assert_eq!(m.col_max().unwrap().data, vec![3.0, 0.5, 2.0]);
+        assert_eq!(m.col_argmax().unwrap(), vec![1, 1, 0]);
+        m.set(0, 0, f64::NAN);
+        assert_eq!(m.col_argmax().unwrap(), vec![1, 1, 0]);
+        assert!(Matrix::new(0, 3).col_max().is_err());
+        m.set(0, 0, 1.0);
+        
+        // Columns of only -inf still have a maximum; columns of only NaN do not
+        let mut extremes = Matrix::new(2, 2);
+        extremes.data = vec![f64::NEG_INFINITY, f64::NAN, f64::NEG_INFINITY, f64::NAN];
+        let maxima = extremes.col_max().unwrap();
+        assert_eq!(maxima.data[0], f64::NEG_INFINITY);
+        assert!(maxima.data[1].is_nan());
+        assert_eq!(extremes.col_argmax(), Err("Column has no comparable value"));
+        extremes.set(1, 1, -3.0);
+        assert_eq!(extremes.col_argmax().unwrap(), vec![0, 1]);
+        
+        // Means over nothing are NaN rather than 0 * inf
+        assert!(Matrix::<f64>::new(2, 0).row_means().data.iter().all(|v| v.is_nan()));
+        assert!(Matrix::<f64>::new(0, 2).col_means().data.iter().all(|v| v.is_nan()));
+        assert_eq!(Matrix::<f64>::new(0, 2).col_means().cols, 2);
+        
+        assert_eq!(m.l1_norm(), 12.5);
+        assert!((m.l2_norm() - 34.25f64.sqrt()).abs() < 1e-12);
+        assert_eq!(m.frobenius_norm(), m.l2_norm());
+    }
+    
+    #[test]
+    fn test_broadcast_and_elementwise() {
+        let column = Matrix::from_array(&[1.0, -2.0]);
+        let broadcast = Matrix::broadcast_column(&column, 3).unwrap();
+        assert_eq!(broadcast.data, vec![1.0, 1.0, 1.0, -2.0, -2.0, -2.0]);
+        
+        let sum = broadcast.add_column(&column).unwrap();
+        assert_eq!(sum.data, vec![2.0, 2.0, 2.0, -4.0, -4.0, -4.0]);
+        assert!(sum.add_column(&Matrix::from_array(&[1.0, 2.0, 3.0])).is_err());
+        assert!(sum.add_column(&Matrix::new(2, 2)).is_err());
+        
+        let mut m = Matrix::from_array(&[0.25, 1.0, 4.0]);
+        m.sqrt_in_place();
+        assert_eq!(m.data, vec![0.5, 1.0, 2.0]);
+        m.powf_in_place(3.0);
+        assert_eq!(m.data, vec![0.125, 1.0, 8.0]);
+        m.ln_in_place();
+        assert!((m.get(2, 0) - 8.0f64.ln()).abs() < 1e-12);
+        m.clamp_in_place(-1.0, 1.0).unwrap();
+        assert_eq!(m.data, vec![-1.0, 0.0, 1.0]);
+        assert!(m.clamp_in_place(1.0, -1.0).is_err());
+        assert!(m.clamp_in_place(f64::NAN, 1.0).is_err());
+        assert!(m.clamp_in_place(0.0, f64::NAN).is_err());
+        assert_eq!(m.data, vec![-1.0, 0.0, 1.0]);
+    }
+    
+    #[test]
+    fn test_parallel_reductions_match_sequential() {
+        use crate::execution::{ExecutionContext, Thresholds};
+        
+        let mut m = Matrix::new(70, 90);
+        m.randomize();
+        let column = Matrix::from_array(&m.data[..70]);
+        let run = |thresholds| {
+            ExecutionContext::new(2, thresholds).unwrap().install(|| {
+                let broadcast = m.add_column(&column).unwrap();
+                (broadcast, m.row_sums(), m.col_sums(), m.col_argmax().unwrap(), m.l1_norm(), m.l2_norm())
+            })
+        };
+        
+        let serial = run(Thresholds::sequential());
+        let parallel = run(Thresholds::uniform(0));
+        assert_eq!(parallel.0, serial.0);
+        assert_eq!(parallel.1, serial.1);
+        assert_eq!(parallel.3, serial.3);
+        for (a, b) in parallel.2.data.iter().zip(&serial.2.data) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        assert!((parallel.4 - serial.4).abs() < 1e-9);
+        assert!((parallel.5 - serial.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,373 @@
+// Classification metrics treat a single-output model as a binary classifier
+// thresholded at 0.5, and a multi-output model as multi-class via argmax.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Average {
+    // Unweighted mean of the per-class scores.
+    Macro,
+    // Computed from the pooled true/false positive counts of all classes.
+    Micro,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    Accuracy,
+    TopKAccuracy(usize),
+    Precision(Average),
+    Recall(Average),
+    F1(Average),
+    RocAuc,
+    MeanSquaredError,
+    MeanAbsoluteError,
+    RootMeanSquaredError,
+    R2,
+}
+
+impl Metric {
+    pub fn name(&self) -> String {
+        let average = |a: &Average| match a {
+            Average::Macro => "macro",
+            Average::Micro => "micro",
+        };
+        match self {
+            Metric::Accuracy => "Accuracy".to_string(),
+            Metric::TopKAccuracy(k) => format!("Top-{} Accuracy", k),
+            Metric::Precision(a) => format!("Precision ({})", average(a)),
+            Metric::Recall(a) => format!("Recall ({})", average(a)),
+            Metric::F1(a) => format!("F1 ({})", average(a)),
+            Metric::RocAuc => "ROC-AUC".to_string(),
+            Metric::MeanSquaredError => "MSE".to_string(),
+            Metric::MeanAbsoluteError => "MAE".to_string(),
+            Metric::RootMeanSquaredError => "RMSE".to_string(),
+            Metric::R2 => "R2".to_string(),
+        }
+    }
+
+    pub fn compute(&self, outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+        match *self {
+            Metric::Accuracy => accuracy(outputs, targets),
+            Metric::TopKAccuracy(k) => top_k_accuracy(outputs, targets, k),
+            Metric::Precision(a) => precision(outputs, targets, a),
+            Metric::Recall(a) => recall(outputs, targets, a),
+            Metric::F1(a) => f1_score(outputs, targets, a),
+            Metric::RocAuc => roc_auc(outputs, targets),
+            Metric::MeanSquaredError => mean_squared_error(outputs, targets),
+            Metric::MeanAbsoluteError => mean_absolute_error(outputs, targets),
+            Metric::RootMeanSquaredError => root_mean_squared_error(outputs, targets),
+            Metric::R2 => r2_score(outputs, targets),
+        }
+    }
+
+    pub fn format_value(&self, value: f64) -> String {
+        match self {
+            Metric::Accuracy | Metric::TopKAccuracy(_) | Metric::Precision(_) | Metric::Recall(_) | Metric::F1(_) => {
+                format!("{:.2}%", value * 100.0)
+            }
+            Metric::RocAuc => format!("{:.4}", value),
+            _ => format!("{:.6}", value),
+        }
+    }
+}
+
+pub fn class_of(values: &[f64]) -> usize {
+    if values.len() == 1 {
+        return if values[0] > 0.5 { 1 } else { 0 };
+    }
+
+    let mut best = 0;
+    for (i, &v) in values.iter().enumerate().skip(1) {
+        if v > values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+// Wide enough for every row, so a target wider than its output still has a class.
+fn num_classes(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> usize {
+    outputs.iter().chain(targets).map(|row| row.len()).max().map_or(0, |width| width.max(2))
+}
+
+pub fn accuracy(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    if outputs.is_empty() {
+        return 0.0;
+    }
+    let correct = outputs.iter().zip(targets)
+        .filter(|(o, t)| class_of(o) == class_of(t))
+        .count();
+    correct as f64 / outputs.len() as f64
+}
+
+pub fn top_k_accuracy(outputs: &[Vec<f64>], targets: &[Vec<f64>], k: usize) -> f64 {
+    if outputs.is_empty() {
+        return 0.0;
+    }
+    let correct = outputs.iter().zip(targets)
+        .filter(|(o, t)| {
+            let target_class = class_of(t);
+            if o.len() == 1 {
+                return class_of(o) == target_class || k >= 2;
+            }
+            // A target class the output has no score for is a miss
+            if target_class >= o.len() {
+                return false;
+            }
+            // Classes ranked strictly above the target; ties count in its favour
+            let above = o.iter().filter(|&&v| v > o[target_class]).count();
+            above < k
+        })
+        .count();
+    correct as f64 / outputs.len() as f64
+}
+
+// `matrix[actual][predicted]` counts the samples of each class pair.
+pub fn confusion_matrix(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = num_classes(outputs, targets);
+    let mut matrix = vec![vec![0; n]; n];
+    for (o, t) in outputs.iter().zip(targets) {
+        matrix[class_of(t)][class_of(o)] += 1;
+    }
+    matrix
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+// Per class: (true positives, predicted positives, actual positives).
+fn class_counts(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> Vec<(usize, usize, usize)> {
+    let matrix = confusion_matrix(outputs, targets);
+    (0..matrix.len())
+        .map(|c| {
+            let predicted = matrix.iter().map(|row| row[c]).sum();
+            let actual = matrix[c].iter().sum();
+            (matrix[c][c], predicted, actual)
+        })
+        .collect()
+}
+
+fn average_scores<F>(outputs: &[Vec<f64>], targets: &[Vec<f64>], average: Average, score: F) -> f64
+where
+    F: Fn(usize, usize, usize) -> f64,
+{
+    let counts = class_counts(outputs, targets);
+    if counts.is_empty() {
+        return 0.0;
+    }
+    match average {
+        Average::Macro => counts.iter().map(|&(tp, p, a)| score(tp, p, a)).sum::<f64>() / counts.len() as f64,
+        Average::Micro => {
+            let (tp, p, a) = counts.iter().fold((0, 0, 0), |acc, c| (acc.0 + c.0, acc.1 + c.1, acc.2 + c.2));
+            score(tp, p, a)
+        }
+    }
+}
+
+pub fn precision(outputs: &[Vec<f64>], targets: &[Vec<f64>], average: Average) -> f64 {
+    average_scores(outputs, targets, average, |tp, predicted, _| ratio(tp, predicted))
+}
+
+pub fn recall(outputs: &[Vec<f64>], targets: &[Vec<f64>], average: Average) -> f64 {
+    average_scores(outputs, targets, average, |tp, _, actual| ratio(tp, actual))
+}
+
+pub fn f1_score(outputs: &[Vec<f64>], targets: &[Vec<f64>], average: Average) -> f64 {
+    average_scores(outputs, targets, average, |tp, predicted, actual| ratio(2 * tp, predicted + actual))
+}
+
+// Area under the ROC curve of `scores` against 0/1 `labels`, via the rank-sum
+// formulation with ties sharing their average rank.
+fn binary_auc(scores: &[f64], labels: &[bool]) -> f64 {
+    let positives = labels.iter().filter(|&&l| l).count();
+    let negatives = labels.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return 0.5;
+    }
+
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut positive_rank_sum = 0.0;
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && scores[order[j + 1]] == scores[order[i]] {
+            j += 1;
+        }
+        let average_rank = (i + j) as f64 / 2.0 + 1.0;
+        for &idx in &order[i..=j] {
+            if labels[idx] {
+                positive_rank_sum += average_rank;
+            }
+        }
+        i = j + 1;
+    }
+
+    let p = positives as f64;
+    (positive_rank_sum - p * (p + 1.0) / 2.0) / (p * negatives as f64)
+}
+
+// Binary for single-output models, one-vs-rest macro average otherwise.
+pub fn roc_auc(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    let width = outputs.first().map_or(0, |o| o.len());
+    if width == 1 {
+        let scores: Vec<f64> = outputs.iter().map(|o| o[0]).collect();
+        let labels: Vec<bool> = targets.iter().map(|t| t[0] > 0.5).collect();
+        return binary_auc(&scores, &labels);
+    }
+
+    let target_classes: Vec<usize> = targets.iter().map(|t| class_of(t)).collect();
+    let aucs: Vec<f64> = (0..width)
+        .filter(|c| target_classes.contains(c))
+        .map(|c| {
+            let scores: Vec<f64> = outputs.iter().map(|o| o[c]).collect();
+            let labels: Vec<bool> = target_classes.iter().map(|&t| t == c).collect();
+            binary_auc(&scores, &labels)
+        })
+        .collect();
+    if aucs.is_empty() {
+        0.5
+    } else {
+        aucs.iter().sum::<f64>() / aucs.len() as f64
+    }
+}
+
+fn pairs<'a>(outputs: &'a [Vec<f64>], targets: &'a [Vec<f64>]) -> impl Iterator<Item = (f64, f64)> + 'a {
+    outputs.iter().zip(targets).flat_map(|(o, t)| o.iter().copied().zip(t.iter().copied()))
+}
+
+pub fn mean_squared_error(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    let (sum, count) = pairs(outputs, targets).fold((0.0, 0), |(s, n), (o, t)| (s + (o - t) * (o - t), n + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+pub fn mean_absolute_error(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    let (sum, count) = pairs(outputs, targets).fold((0.0, 0), |(s, n), (o, t)| (s + (o - t).abs(), n + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+pub fn root_mean_squared_error(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    mean_squared_error(outputs, targets).sqrt()
+}
+
+pub fn r2_score(outputs: &[Vec<f64>], targets: &[Vec<f64>]) -> f64 {
+    let (sum, count) = pairs(outputs, targets).fold((0.0, 0), |(s, n), (_, t)| (s + t, n + 1));
+    if count == 0 {
+        return 0.0;
+    }
+    let mean = sum / count as f64;
+
+    let (residual, total) = pairs(outputs, targets)
+        .fold((0.0, 0.0), |(r, tot), (o, t)| (r + (t - o) * (t - o), tot + (t - mean) * (t - mean)));
+    if total == 0.0 {
+        return if residual == 0.0 { 1.0 } else { 0.0 };
+    }
+    1.0 - residual / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_hot(classes: &[usize], n: usize) -> Vec<Vec<f64>> {
+        classes.iter()
+            .map(|&c| {
+                let mut v = vec![0.0; n];
+                v[c] = 1.0;
+                v
+            })
+            .collect()
+    }
+
+    fn multi_class() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let outputs = vec![
+            vec![0.7, 0.2, 0.1],
+            vec![0.1, 0.6, 0.3],
+            vec![0.3, 0.4, 0.3],
+            vec![0.2, 0.3, 0.5],
+            vec![0.5, 0.1, 0.4],
+        ];
+        (outputs, one_hot(&[0, 1, 2, 2, 2], 3))
+    }
+
+    #[test]
+    fn test_accuracy_uses_argmax() {
+        let (outputs, targets) = multi_class();
+        assert!((accuracy(&outputs, &targets) - 0.6).abs() < 1e-12);
+        // Softmax-like outputs never cross 0.5 on every class, but argmax still counts them
+        assert_eq!(accuracy(&[vec![0.4, 0.35, 0.25]], &one_hot(&[0], 3)), 1.0);
+        assert_eq!(accuracy(&[vec![0.7], vec![0.2]], &[vec![1.0], vec![1.0]]), 0.5);
+    }
+
+    #[test]
+    fn test_top_k_accuracy() {
+        let (outputs, targets) = multi_class();
+        assert!((top_k_accuracy(&outputs, &targets, 1) - 0.6).abs() < 1e-12);
+        assert!((top_k_accuracy(&outputs, &targets, 2) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_confusion_matrix_and_scores() {
+        let (outputs, targets) = multi_class();
+        let matrix = confusion_matrix(&outputs, &targets);
+        assert_eq!(matrix, vec![vec![1, 0, 0], vec![0, 1, 0], vec![1, 1, 1]]);
+
+        // Per class precision: 1/2, 1/2, 1/1; recall: 1/1, 1/1, 1/3
+        assert!((precision(&outputs, &targets, Average::Macro) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((recall(&outputs, &targets, Average::Macro) - 7.0 / 9.0).abs() < 1e-12);
+        let f1 = (2.0 / 3.0 + 2.0 / 3.0 + 0.5) / 3.0;
+        assert!((f1_score(&outputs, &targets, Average::Macro) - f1).abs() < 1e-12);
+
+        // Micro averages reduce to accuracy for single-label multi-class data
+        assert!((precision(&outputs, &targets, Average::Micro) - 0.6).abs() < 1e-12);
+        assert!((recall(&outputs, &targets, Average::Micro) - 0.6).abs() < 1e-12);
+        assert!((f1_score(&outputs, &targets, Average::Micro) - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mismatched_widths() {
+        // Targets of class 2 against two-wide outputs
+        let outputs = vec![vec![0.8, 0.2], vec![0.3, 0.7]];
+        let targets = one_hot(&[2, 1], 3);
+        assert_eq!(top_k_accuracy(&outputs, &targets, 2), 0.5);
+        assert_eq!(confusion_matrix(&outputs, &targets), vec![vec![0, 0, 0], vec![0, 1, 0], vec![1, 0, 0]]);
+        assert_eq!(confusion_matrix(&targets, &outputs), vec![vec![0, 0, 1], vec![0, 1, 0], vec![0, 0, 0]]);
+        assert!((recall(&outputs, &targets, Average::Micro) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_roc_auc() {
+        let outputs = vec![vec![0.1], vec![0.4], vec![0.35], vec![0.8]];
+        let targets = vec![vec![0.0], vec![0.0], vec![1.0], vec![1.0]];
+        assert!((roc_auc(&outputs, &targets) - 0.75).abs() < 1e-12);
+
+        let perfect = vec![vec![0.9, 0.1], vec![0.2, 0.8]];
+        assert_eq!(roc_auc(&perfect, &one_hot(&[0, 1], 2)), 1.0);
+
+        let tied = vec![vec![0.5], vec![0.5]];
+        assert_eq!(roc_auc(&tied, &[vec![0.0], vec![1.0]]), 0.5);
+    }
+
+    #[test]
+    fn test_regression_metrics() {
+        let outputs = vec![vec![2.5], vec![0.0], vec![2.0], vec![8.0]];
+        let targets = vec![vec![3.0], vec![-0.5], vec![2.0], vec![7.0]];
+        assert!((mean_squared_error(&outputs, &targets) - 0.375).abs() < 1e-12);
+        assert!((mean_absolute_error(&outputs, &targets) - 0.5).abs() < 1e-12);
+        assert!((root_mean_squared_error(&outputs, &targets) - 0.375f64.sqrt()).abs() < 1e-12);
+        assert!((r2_score(&outputs, &targets) - 0.9486081370449679).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_metric_enum() {
+        let (outputs, targets) = multi_class();
+        assert_eq!(Metric::Accuracy.compute(&outputs, &targets), accuracy(&outputs, &targets));
+        assert_eq!(Metric::F1(Average::Macro).name(), "F1 (macro)");
+        assert_eq!(Metric::Accuracy.format_value(0.5), "50.00%");
+        assert_eq!(Metric::MeanSquaredError.format_value(0.25), "0.250000");
+    }
+}
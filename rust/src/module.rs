@@ -0,0 +1,102 @@
+use crate::attention::{MultiHeadAttention, TransformerEncoderBlock};
+use crate::conv1d::Conv1D;
+use crate::embedding::Embedding;
+use crate::layer::Layer;
+use crate::matrix::Matrix;
+use crate::normalization::LayerNorm;
+use crate::pooling::{AvgPool2D, Flatten, GlobalAveragePool, MaxPool2D};
+use crate::recurrent::{Recurrent, RecurrentCell};
+
+// Anything that maps one matrix to another and can push an error back through
+// itself. Every layer type implements this so they can be wired into a `Graph`.
+pub trait Module: Send {
+    fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str>;
+    fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str>;
+
+    // Every trainable matrix, so tools like the gradient checker can save and
+    // restore them. Modules without parameters keep the default.
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        Vec::new()
+    }
+
+    // Penalty from weight regularizers, added to the reported loss.
+    fn regularization_penalty(&self) -> f64 {
+        0.0
+    }
+}
+
+impl Module for Layer {
+    fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        Layer::feed_forward(self, input)
+    }
+
+    fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        Layer::backpropagate(self, output_error, learning_rate)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        Layer::parameters_mut(self)
+    }
+
+    fn regularization_penalty(&self) -> f64 {
+        Layer::regularization_penalty(self)
+    }
+}
+
+macro_rules! impl_module {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl Module for $t {
+                fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+                    <$t>::feed_forward(self, input)
+                }
+
+                fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+                    <$t>::backpropagate(self, output_error, learning_rate)
+                }
+            }
+        )*
+    };
+    (with parameters: $($t:ty),* $(,)?) => {
+        $(
+            impl Module for $t {
+                fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+                    <$t>::feed_forward(self, input)
+                }
+
+                fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+                    <$t>::backpropagate(self, output_error, learning_rate)
+                }
+
+                fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+                    <$t>::parameters_mut(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_module!(MaxPool2D, AvgPool2D, GlobalAveragePool, Flatten);
+
+impl_module!(
+    with parameters:
+    Conv1D,
+    Embedding,
+    LayerNorm,
+    MultiHeadAttention,
+    TransformerEncoderBlock,
+);
+
+impl<C: RecurrentCell> Module for Recurrent<C> {
+    fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        Recurrent::feed_forward(self, input)
+    }
+
+    fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        Recurrent::backpropagate(self, output_error, learning_rate)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        Recurrent::parameters_mut(self)
+    }
+}
@@ -1,22 +1,180 @@
 use crate::activation::ActivationFunction;
+use crate::checkpoint::{Checkpoint, CheckpointPolicy, Checkpointing};
+use crate::clipping::GradientClipping;
+use crate::divergence::{Divergence, DivergencePolicy, NonFiniteSource, TrainingError};
+use crate::execution::ExecutionContext;
+use crate::float::Float;
 use crate::layer::Layer;
+use crate::loss::LossFunction;
 use crate::matrix::Matrix;
+use crate::metrics::{self, Metric};
+use crate::parallel::{HogwildStep, Replica, SharedWeights, TrainingMode};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::fmt;
 use std::sync::Arc;
 
-pub struct NeuralNetwork {
-    layers: Vec<Layer>,
+const EVALUATION_BATCH_SIZE: usize = 64;
+
+// Inputs and targets, one sample per entry.
+type Dataset<T> = (Vec<Vec<T>>, Vec<Vec<T>>);
+
+// Losses and metrics are computed in f64 whatever the network's element type.
+fn to_f64_rows<T: Float>(rows: &[Vec<T>]) -> Vec<Vec<f64>> {
+    rows.iter().map(|row| row.iter().map(|v| v.to_f64()).collect()).collect()
+}
+
+// A sample's loss, and where training first produced a non-finite value.
+pub(crate) type SampleOutcome = (f64, Option<(NonFiniteSource, usize)>);
+
+// Runs `input` through every layer, leaving each activation in its layer.
+// With `check`, stops at and returns the first layer whose output is not finite.
+pub(crate) fn forward_layers<T: Float>(layers: &mut [Layer<T>], input: &Matrix<T>, check: bool) -> Result<Option<usize>, &'static str> {
+    for i in 0..layers.len() {
+        let (previous, rest) = layers.split_at_mut(i);
+        let input = match previous.last() {
+            Some(layer) => layer.output().ok_or("No activation stored for backpropagation")?,
+            None => input,
+        };
+        let output = rest[0].forward(input)?;
+        if check && !output.is_finite() {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+// Backpropagates `error` from the last layer down, leaving every layer's
+// gradients pending.
+pub(crate) fn backward_layers<T: Float>(layers: &mut [Layer<T>], error: &Matrix<T>) -> Result<(), &'static str> {
+    for i in (0..layers.len()).rev() {
+        let (current, next) = layers.split_at_mut(i + 1);
+        let error = match next.first() {
+            Some(layer) => layer.input_error(),
+            None => error,
+        };
+        current[i].backward(error)?;
+    }
+    Ok(())
+}
+
+// f64 copies of a sample's output and target and the loss gradient, which
+// `LossFunction` works on. Kept between samples so they are only sized once.
+#[derive(Default)]
+pub(crate) struct LossScratch {
+    output: Vec<f64>,
+    target: Vec<f64>,
+    gradient: Vec<f64>,
+}
+
+// Writes the update direction for one sample's output into `error` and returns
+// the sample's loss. Without a loss function that is `target - output` and
+// mean squared error.
+pub(crate) fn sample_error<T: Float>(
+    loss: Option<&Arc<dyn LossFunction>>,
+    output: &[T],
+    target: &[T],
+    error: &mut [T],
+    scratch: &mut LossScratch,
+) -> f64 {
+    match loss {
+        Some(loss) => {
+            let LossScratch { output: output64, target: target64, gradient } = scratch;
+            output64.clear();
+            output64.extend(output.iter().map(|v| v.to_f64()));
+            target64.clear();
+            target64.extend(target.iter().map(|v| v.to_f64()));
+            gradient.resize(output.len(), 0.0);
+            loss.gradient_into(output64, target64, gradient);
+            for (e, g) in error.iter_mut().zip(gradient.iter()) {
+                *e = T::from_f64(-g);
+            }
+            loss.loss(output64, target64)
+        }
+        None => {
+            for ((e, &t), &o) in error.iter_mut().zip(target).zip(output) {
+                *e = t - o;
+            }
+            error.iter().map(|e| e.to_f64() * e.to_f64()).sum::<f64>() / error.len() as f64
+        }
+    }
+}
+
+pub struct EvaluationReport {
+    pub samples: usize,
+    pub loss: f64,
+    pub metrics: Vec<(Metric, f64)>,
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} samples - Loss: {:.6}", self.samples, self.loss)?;
+        for (metric, value) in &self.metrics {
+            write!(f, " - {}: {}", metric.name(), metric.format_value(*value))?;
+        }
+        Ok(())
+    }
+}
+
+pub struct NeuralNetwork<T: Float = f64> {
+    layers: Vec<Layer<T>>,
     learning_rate: f64,
+    gradient_clipping: Option<GradientClipping>,
+    divergence_check: Option<DivergencePolicy>,
+    last_divergence: Option<Divergence>,
+    metrics: Vec<Metric>,
+    loss: Option<Arc<dyn LossFunction>>,
+    rng: ChaCha8Rng,
+    validation: Option<Dataset<T>>,
+    checkpointing: Option<Checkpointing>,
+    best_validation_loss: Option<f64>,
+    // Completed epochs restored from a checkpoint; the next `fit` starts here.
+    start_epoch: usize,
+    execution: Option<ExecutionContext>,
+    training_mode: TrainingMode,
+    // Worker copies for data-parallel and Hogwild training, rebuilt by each `fit`.
+    replicas: Vec<Replica<T>>,
+    // Per-sample input and output error, reused across `train` calls.
+    scratch_input: Matrix<T>,
+    scratch_error: Matrix<T>,
+    scratch_loss: LossScratch,
 }
 
+// As with `Matrix::new`, `new` builds the default f64 network; use
+// `NeuralNetwork::<f32>::with_learning_rate` for other element types.
 impl NeuralNetwork {
     pub fn new(learning_rate: f64) -> Self {
+        NeuralNetwork::with_learning_rate(learning_rate)
+    }
+}
+
+impl<T: Float> NeuralNetwork<T> {
+    pub fn with_learning_rate(learning_rate: f64) -> Self {
         NeuralNetwork {
             layers: Vec::new(),
             learning_rate,
+            gradient_clipping: None,
+            divergence_check: None,
+            last_divergence: None,
+            metrics: vec![Metric::Accuracy],
+            loss: None,
+            rng: ChaCha8Rng::from_entropy(),
+            validation: None,
+            checkpointing: None,
+            best_validation_loss: None,
+            start_epoch: 0,
+            execution: None,
+            training_mode: TrainingMode::Serial,
+            replicas: Vec::new(),
+            scratch_input: Matrix::zeros(0, 0),
+            scratch_error: Matrix::zeros(0, 0),
+            scratch_loss: LossScratch::default(),
         }
     }
 
-    pub fn add_layer(&mut self, output_size: usize, activation: Arc<dyn ActivationFunction>) -> Result<(), &'static str> {
+    pub fn add_layer(&mut self, output_size: usize, activation: Arc<dyn ActivationFunction<T>>) -> Result<(), &'static str> {
         if self.layers.is_empty() {
             return Err("Must specify input size for the first layer");
         }
@@ -26,7 +184,7 @@ impl NeuralNetwork {
         Ok(())
     }
 
-    pub fn add_input_layer(&mut self, input_size: usize, output_size: usize, activation: Arc<dyn ActivationFunction>) -> Result<(), &'static str> {
+    pub fn add_input_layer(&mut self, input_size: usize, output_size: usize, activation: Arc<dyn ActivationFunction<T>>) -> Result<(), &'static str> {
         if !self.layers.is_empty() {
             return Err("Input layer must be added first");
         }
@@ -35,7 +193,166 @@ impl NeuralNetwork {
         Ok(())
     }
 
-    pub fn predict(&mut self, input_array: &[f64]) -> Result<Vec<f64>, &'static str> {
+    // Seeds the RNG used to shuffle training data in `fit`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    // Held-out data scored after every epoch of `fit`.
+    pub fn set_validation_data(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>]) {
+        self.validation = Some((inputs.to_vec(), targets.to_vec()));
+    }
+
+    pub fn clear_validation_data(&mut self) {
+        self.validation = None;
+    }
+
+    pub fn set_checkpointing(&mut self, checkpointing: Option<Checkpointing>) {
+        self.checkpointing = checkpointing;
+    }
+
+    pub fn checkpoint(&self, epoch: usize) -> Checkpoint {
+        Checkpoint {
+            epoch,
+            learning_rate: self.learning_rate,
+            layers: self.layers.iter().map(|l| (l.weights.cast(), l.biases.cast())).collect(),
+            rng: self.rng.clone(),
+            best_validation_loss: self.best_validation_loss,
+        }
+    }
+
+    // Loads a checkpoint into a network with the same architecture. The next
+    // call to `fit` resumes after the checkpoint's epoch, so passing the original
+    // epoch count reproduces the uninterrupted run exactly.
+    pub fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), &'static str> {
+        if checkpoint.layers.len() != self.layers.len() {
+            return Err("Checkpoint layer count does not match network");
+        }
+        for (layer, (weights, biases)) in self.layers.iter().zip(&checkpoint.layers) {
+            if (layer.weights.rows, layer.weights.cols) != (weights.rows, weights.cols)
+                || (layer.biases.rows, layer.biases.cols) != (biases.rows, biases.cols)
+            {
+                return Err("Checkpoint layer shapes do not match network");
+            }
+        }
+
+        for (layer, (weights, biases)) in self.layers.iter_mut().zip(&checkpoint.layers) {
+            layer.weights = weights.cast();
+            layer.biases = biases.cast();
+        }
+        self.learning_rate = checkpoint.learning_rate;
+        self.rng = checkpoint.rng.clone();
+        self.best_validation_loss = checkpoint.best_validation_loss;
+        self.start_epoch = checkpoint.epoch;
+        Ok(())
+    }
+
+    fn save_checkpoints(&mut self, completed_epochs: usize, validation_loss: Option<f64>) -> Result<(), TrainingError> {
+        let Some(checkpointing) = &self.checkpointing else {
+            return Ok(());
+        };
+        
+        let improved = match (validation_loss, self.best_validation_loss) {
+            (Some(loss), Some(best)) => loss < best,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if improved {
+            self.best_validation_loss = validation_loss;
+        }
+        
+        let path = match checkpointing.policy {
+            CheckpointPolicy::EveryEpochs(n) if n > 0 && completed_epochs.is_multiple_of(n) => {
+                checkpointing.directory.join(format!("epoch-{:04}.ckpt", completed_epochs))
+            }
+            CheckpointPolicy::BestValidation if improved => checkpointing.directory.join("best.ckpt"),
+            _ => return Ok(()),
+        };
+        
+        self.checkpoint(completed_epochs).save(path)
+            .map_err(|e| TrainingError::Checkpoint(e.to_string()))
+    }
+
+    // Runs `fit`, `train`, `predict` and `evaluate` on the context's pool
+    // instead of rayon's global one. Calls from outside the pool pay a thread
+    // hand-off, so prefer `fit` or wrap a training loop in `install` yourself.
+    pub fn set_execution_context(&mut self, context: Option<ExecutionContext>) {
+        self.execution = context;
+    }
+
+    pub fn execution_context(&self) -> Option<&ExecutionContext> {
+        self.execution.as_ref()
+    }
+
+    pub fn set_training_mode(&mut self, mode: TrainingMode) {
+        self.training_mode = mode;
+    }
+
+    pub fn training_mode(&self) -> TrainingMode {
+        self.training_mode
+    }
+
+    pub fn set_gradient_clipping(&mut self, clipping: Option<GradientClipping>) -> Result<(), &'static str> {
+        if let Some(clipping) = clipping {
+            clipping.validate()?;
+        }
+        self.gradient_clipping = clipping;
+        Ok(())
+    }
+
+    // When set, `fit` checks activations, gradients and weights for NaN/Inf
+    // after every sample and handles the first occurrence with `policy`.
+    pub fn set_divergence_check(&mut self, policy: Option<DivergencePolicy>) {
+        self.divergence_check = policy;
+    }
+
+    // Metrics reported by `fit` alongside the loss. Defaults to accuracy.
+    pub fn set_metrics(&mut self, metrics: Vec<Metric>) {
+        self.metrics = metrics;
+    }
+
+    pub fn metrics(&self) -> &[Metric] {
+        &self.metrics
+    }
+
+    // Loss minimised by `train`/`fit` and reported by `evaluate`. Without one the
+    // network backpropagates `target - output` and reports mean squared error.
+    pub fn set_loss(&mut self, loss: Option<Arc<dyn LossFunction>>) {
+        self.loss = loss;
+    }
+
+    fn sample_loss(&self, output: &[f64], target: &[f64]) -> f64 {
+        match &self.loss {
+            Some(loss) => loss.loss(output, target),
+            None => metrics::mean_squared_error(&[output.to_vec()], &[target.to_vec()]),
+        }
+    }
+
+    // The divergence that made the last `fit` roll back, if any.
+    pub fn last_divergence(&self) -> Option<&Divergence> {
+        self.last_divergence.as_ref()
+    }
+
+    pub fn layers(&self) -> &[Layer<T>] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut Layer<T>> {
+        self.layers.get_mut(index)
+    }
+
+    pub fn regularization_penalty(&self) -> f64 {
+        self.layers.iter().map(|layer| layer.regularization_penalty()).sum()
+    }
+
+    pub fn predict(&mut self, input_array: &[T]) -> Result<Vec<T>, &'static str> {
+        match self.execution.clone() {
+            Some(context) => context.install(|| self.predict_sample(input_array)),
+            None => self.predict_sample(input_array),
+        }
+    }
+
+    fn predict_sample(&mut self, input_array: &[T]) -> Result<Vec<T>, &'static str> {
         let mut input = Matrix::from_array(input_array);
         
         for layer in &mut self.layers {
@@ -45,27 +362,161 @@ impl NeuralNetwork {
         Ok(input.to_array())
     }
 
-    pub fn train(&mut self, input_array: &[f64], target_array: &[f64]) -> Result<(), &'static str> {
-        let input = Matrix::from_array(input_array);
-        let target = Matrix::from_array(target_array);
+    pub fn train(&mut self, input_array: &[T], target_array: &[T]) -> Result<(), &'static str> {
+        match self.execution.clone() {
+            Some(context) => context.install(|| self.train_sample(input_array, target_array, false))?,
+            None => self.train_sample(input_array, target_array, false)?,
+        };
+        Ok(())
+    }
+
+    // Returns the sample's loss before the update, and the first non-finite value
+    // found and its layer when `check` is set. Activations and errors stay in each
+    // layer's buffers, so a step doesn't allocate once the buffers have been
+    // sized, as long as a configured loss overrides `gradient_into`.
+    fn train_sample(&mut self, input_array: &[T], target_array: &[T], check: bool) -> Result<SampleOutcome, &'static str> {
+        let input = &mut self.scratch_input;
+        input.resize(input_array.len(), 1);
+        input.data.copy_from_slice(input_array);
         
-        let mut output = input;
-        for layer in &mut self.layers {
-            output = layer.feed_forward(&output)?;
+        if let Some(i) = forward_layers(&mut self.layers, &self.scratch_input, check)? {
+            return Ok((f64::NAN, Some((NonFiniteSource::Activation, i))));
+        }
+        
+        let output = match self.layers.last() {
+            Some(layer) => layer.output().ok_or("No activation stored for backpropagation")?,
+            None => &self.scratch_input,
+        };
+        if output.data.len() != target_array.len() {
+            return Err("Matrix dimensions don't match for subtraction");
         }
         
-        let mut error = target.subtract(&output)?;
+        self.scratch_error.resize(output.rows, 1);
+        let loss = sample_error(self.loss.as_ref(), &output.data, target_array, &mut self.scratch_error.data, &mut self.scratch_loss);
         
-        for i in (0..self.layers.len()).rev() {
-            error = self.layers[i].backpropagate(&error, self.learning_rate)?;
+        backward_layers(&mut self.layers, &self.scratch_error)?;
+        self.apply_step(loss, check)
+    }
+
+    // One update from the mean gradient over `batch`, whose samples are split
+    // into contiguous shards that the replicas work through in parallel. Shard
+    // gradients are summed in shard order, so the result does not depend on
+    // how many threads ran them.
+    fn train_batch(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>], batch: &[usize], check: bool) -> Result<SampleOutcome, &'static str> {
+        let shard_size = batch.len().div_ceil(self.replicas.len());
+        let shards = batch.len().div_ceil(shard_size);
+        let replicas = &mut self.replicas[..shards];
+        for replica in replicas.iter_mut() {
+            replica.sync(&self.layers);
         }
         
-        Ok(())
+        let loss_function = self.loss.as_ref();
+        let outcomes = replicas.par_iter_mut()
+            .zip(batch.par_chunks(shard_size))
+            .map(|(replica, shard)| replica.gradients(inputs, targets, shard, loss_function, check))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(&(_, Some(divergence))) = outcomes.iter().find(|(_, d)| d.is_some()) {
+            return Ok((f64::NAN, Some(divergence)));
+        }
+        
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            layer.average_gradients(replicas.iter().map(|r| &r.layers()[i]), batch.len())?;
+        }
+        let loss = outcomes.iter().map(|(loss, _)| loss).sum();
+        self.apply_step(loss, check)
+    }
+
+    // One Hogwild pass over `samples`, dealt out to the replicas in contiguous
+    // runs. The workers update shared atomic copies of the weights, which are
+    // copied back into the layers once they have all finished.
+    fn train_hogwild(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>], samples: &[usize]) -> Result<SampleOutcome, &'static str> {
+        let shared = SharedWeights::new(&self.layers);
+        let share = samples.len().div_ceil(self.replicas.len());
+        let step = HogwildStep {
+            loss: self.loss.as_ref(),
+            clipping: self.gradient_clipping,
+            learning_rate: self.learning_rate,
+        };
+        
+        let losses = self.replicas.par_iter_mut()
+            .zip(samples.par_chunks(share))
+            .map(|(replica, samples)| replica.hogwild(&shared, inputs, targets, samples, step))
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        shared.load_into(&mut self.layers);
+        Ok((losses.iter().sum(), None))
+    }
+
+    // Clips, checks and applies the gradients the layers hold after a backward
+    // pass. `loss` is passed through to the outcome.
+    fn apply_step(&mut self, loss: f64, check: bool) -> Result<SampleOutcome, &'static str> {
+        if let Some(clipping) = self.gradient_clipping {
+            clipping.apply_to_layers(&mut self.layers);
+        }
+        
+        if check {
+            for (i, layer) in self.layers.iter_mut().enumerate() {
+                if let Some((w, b)) = layer.gradients_mut() {
+                    if !w.is_finite() || !b.is_finite() {
+                        return Ok((loss, Some((NonFiniteSource::Gradient, i))));
+                    }
+                }
+            }
+        }
+        
+        for layer in &mut self.layers {
+            layer.apply_gradients(self.learning_rate);
+        }
+        
+        if check {
+            for (i, layer) in self.layers.iter().enumerate() {
+                if !layer.weights.is_finite() || !layer.biases.is_finite() {
+                    return Ok((loss, Some((NonFiniteSource::Weight, i))));
+                }
+            }
+        }
+        
+        Ok((loss, None))
     }
 
-    pub fn fit(&mut self, inputs: &[Vec<f64>], targets: &[Vec<f64>], epochs: usize, verbose: bool) -> Result<(), &'static str> {
+    pub fn fit(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>], epochs: usize, verbose: bool) -> Result<(), TrainingError> {
+        match self.execution.clone() {
+            Some(context) => context.install(|| self.fit_epochs(inputs, targets, epochs, verbose)),
+            None => self.fit_epochs(inputs, targets, epochs, verbose),
+        }
+    }
+
+    fn fit_epochs(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>], epochs: usize, verbose: bool) -> Result<(), TrainingError> {
         if inputs.is_empty() || targets.is_empty() || inputs.len() != targets.len() {
-            return Err("Invalid input/target data");
+            return Err(TrainingError::Invalid("Invalid input/target data"));
+        }
+        
+        self.last_divergence = None;
+        let check = self.divergence_check.is_some();
+        
+        let needs_validation = matches!(&self.checkpointing, Some(c) if c.policy == CheckpointPolicy::BestValidation);
+        if needs_validation && self.validation.is_none() {
+            return Err(TrainingError::Invalid("Best-validation checkpointing needs validation data"));
+        }
+        
+        let batch_size = self.training_mode.batch_size().map_err(TrainingError::Invalid)?;
+        let workers = |count: usize| if count == 0 { rayon::current_num_threads() } else { count };
+        self.replicas = match self.training_mode {
+            TrainingMode::Serial => Vec::new(),
+            TrainingMode::DataParallel { shards, .. } => {
+                (0..workers(shards).min(batch_size)).map(|_| Replica::new(&self.layers)).collect()
+            }
+            TrainingMode::Hogwild { .. } if check => {
+                return Err(TrainingError::Invalid("Divergence checks are not supported with Hogwild training"));
+            }
+            TrainingMode::Hogwild { threads } => {
+                (0..workers(threads)).map(|_| Replica::regularized(&self.layers)).collect()
+            }
+        };
+        
+        let start_epoch = std::mem::take(&mut self.start_epoch);
+        if start_epoch == 0 {
+            self.best_validation_loss = None;
         }
         
         
@@ -80,98 +531,194 @@ impl NeuralNetwork {
         
         
         let data_size = inputs.len();
-        let mut batch_indices: Vec<usize> = (0..data_size).collect();
         
-        for epoch in 0..epochs {
+        for epoch in start_epoch..epochs {
             let mut total_loss = 0.0;
             
             
+            let snapshot: Vec<(Matrix<T>, Matrix<T>)> = if self.divergence_check == Some(DivergencePolicy::Rollback) {
+                self.layers.iter().map(|l| (l.weights.clone(), l.biases.clone())).collect()
+            } else {
+                Vec::new()
+            };
+            
+            
+            // Reshuffled from scratch each epoch so the order depends only on the RNG
+            let mut batch_indices: Vec<usize> = (0..data_size).collect();
             if data_size > 10 {
-                use rand::seq::SliceRandom;
-                use rand::thread_rng;
-                batch_indices.shuffle(&mut thread_rng());
+                batch_indices.shuffle(&mut self.rng);
             }
             
             
-            for &i in &batch_indices {
-                let output = self.predict(&inputs[i])?;
-                let target = &targets[i];
+            // A divergence in a mini-batch is reported against its first sample
+            for batch in batch_indices.chunks(batch_size) {
+                let (loss, divergence) = match self.training_mode {
+                    TrainingMode::Serial => self.train_sample(&inputs[batch[0]], &targets[batch[0]], check)?,
+                    TrainingMode::DataParallel { .. } => self.train_batch(inputs, targets, batch, check)?,
+                    TrainingMode::Hogwild { .. } => self.train_hogwild(inputs, targets, batch)?,
+                };
+                total_loss += loss;
                 
                 
-                let mut sample_loss = 0.0;
-                for j in 0..output.len() {
-                    let error = target[j] - output[j];
-                    sample_loss += error * error;
+                if let Some((source, layer)) = divergence {
+                    let divergence = Divergence { source, layer, epoch: epoch + 1, sample: batch[0] };
+                    
+                    if self.divergence_check == Some(DivergencePolicy::Abort) {
+                        return Err(TrainingError::Diverged(divergence));
+                    }
+                    
+                    for (layer, (weights, biases)) in self.layers.iter_mut().zip(snapshot) {
+                        layer.weights = weights;
+                        layer.biases = biases;
+                    }
+                    if verbose {
+                        println!("{} - rolled back to the weights from epoch {}", divergence, epoch);
+                    }
+                    self.last_divergence = Some(divergence);
+                    return Ok(());
                 }
-                sample_loss /= output.len() as f64;
-                total_loss += sample_loss;
-                
-                
-                self.train(&inputs[i], target)?;
             }
             
-            let avg_loss = total_loss / data_size as f64;
+            let avg_loss = total_loss / data_size as f64 + self.regularization_penalty();
             
+            let validation_loss = match &self.validation {
+                Some((inputs, targets)) => Some(self.evaluate(inputs, targets)?.loss),
+                None => None,
+            };
+            self.save_checkpoints(epoch + 1, validation_loss)?;
             
             if verbose && (epoch % report_frequency == 0 || epoch == epochs - 1) {
-                let accuracy = self.calculate_accuracy(inputs, targets)?;
-                println!("Epoch {}/{} - Loss: {:.6} - Accuracy: {:.2}%", 
-                         epoch + 1, epochs, avg_loss, accuracy * 100.0);
+                let mut line = format!("Epoch {}/{} - Loss: {:.6}", epoch + 1, epochs, avg_loss);
+                if let Some(loss) = validation_loss {
+                    line.push_str(&format!(" - Val Loss: {:.6}", loss));
+                }
+                for (metric, value) in self.compute_metrics(inputs, targets, &self.metrics.clone())? {
+                    line.push_str(&format!(" - {}: {}", metric.name(), metric.format_value(value)));
+                }
+                println!("{}", line);
             }
         }
         
         Ok(())
     }
     
-    pub fn calculate_accuracy(&mut self, inputs: &[Vec<f64>], targets: &[Vec<f64>]) -> Result<f64, &'static str> {
+    // Scores a held-out set with the configured loss and metrics. Samples are
+    // packed into column batches and the batches run in parallel.
+    pub fn evaluate(&self, inputs: &[Vec<T>], targets: &[Vec<T>]) -> Result<EvaluationReport, &'static str> {
+        match &self.execution {
+            Some(context) => context.install(|| self.evaluate_batches(inputs, targets)),
+            None => self.evaluate_batches(inputs, targets),
+        }
+    }
+
+    fn evaluate_batches(&self, inputs: &[Vec<T>], targets: &[Vec<T>]) -> Result<EvaluationReport, &'static str> {
         if inputs.is_empty() || targets.is_empty() || inputs.len() != targets.len() {
             return Err("Invalid input/target data");
         }
         
+        let batches = inputs.par_chunks(EVALUATION_BATCH_SIZE)
+            .map(|batch| self.infer_batch(batch))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs: Vec<Vec<f64>> = batches.into_iter().flatten().collect();
+        let targets = to_f64_rows(targets);
         
-        let max_samples = if cfg!(test) { 100 } else { inputs.len() };
-        let sample_count = inputs.len().min(max_samples);
-        
-        
-        let indices: Vec<usize> = if sample_count < inputs.len() {
-            let step = inputs.len() / sample_count;
-            (0..sample_count).map(|i| i * step).collect()
-        } else {
-            (0..inputs.len()).collect()
-        };
+        if outputs.iter().zip(&targets).any(|(o, t)| o.len() != t.len()) {
+            return Err("Output and target sizes don't match");
+        }
         
-        let mut correct = 0;
+        let total_loss: f64 = outputs.par_iter().zip(targets.par_iter())
+            .map(|(o, t)| self.sample_loss(o, t))
+            .sum();
         
+        Ok(EvaluationReport {
+            samples: inputs.len(),
+            loss: total_loss / inputs.len() as f64 + self.regularization_penalty(),
+            metrics: self.metrics.iter().map(|m| (*m, m.compute(&outputs, &targets))).collect(),
+        })
+    }
+    
+    fn infer_batch(&self, batch: &[Vec<T>]) -> Result<Vec<Vec<f64>>, &'static str> {
+        let input_size = self.layers.first().ok_or("Network has no layers")?.weights.cols;
+        if batch.iter().any(|sample| sample.len() != input_size) {
+            return Err("Input size does not match network");
+        }
         
-        for &i in &indices {
-            let output = self.predict(&inputs[i])?;
-            let target = &targets[i];
-            
-            
-            let mut is_correct = true;
-            
-            for j in 0..output.len() {
-                let predicted = if output[j] > 0.5 { 1.0 } else { 0.0 };
-                if (predicted - target[j]).abs() > 0.01 {
-                    is_correct = false;
-                    break;
-                }
-            }
-            
-            if is_correct {
-                correct += 1;
+        let mut activations = Matrix::zeros(input_size, batch.len());
+        for (col, sample) in batch.iter().enumerate() {
+            for (row, &value) in sample.iter().enumerate() {
+                activations.data[row * batch.len() + col] = value;
             }
         }
+        for layer in &self.layers {
+            activations = layer.infer(&activations)?;
+        }
         
+        Ok((0..batch.len())
+            .map(|col| (0..activations.rows).map(|row| activations.data[row * activations.cols + col].to_f64()).collect())
+            .collect())
+    }
+    
+    pub fn compute_metrics(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>], metrics: &[Metric]) -> Result<Vec<(Metric, f64)>, &'static str> {
+        if inputs.is_empty() || targets.is_empty() || inputs.len() != targets.len() {
+            return Err("Invalid input/target data");
+        }
+        
+        let outputs: Vec<Vec<f64>> = inputs.iter()
+            .map(|input| self.predict(input).map(|output| output.iter().map(|v| v.to_f64()).collect()))
+            .collect::<Result<_, _>>()?;
+        let targets = to_f64_rows(targets);
+        if outputs.iter().zip(&targets).any(|(o, t)| o.len() != t.len()) {
+            return Err("Output and target sizes don't match");
+        }
         
-        Ok(correct as f64 / sample_count as f64)
+        Ok(metrics.iter().map(|m| (*m, m.compute(&outputs, &targets))).collect())
+    }
+    
+    // Argmax accuracy for multi-output networks, 0.5 threshold for a single output.
+    pub fn calculate_accuracy(&mut self, inputs: &[Vec<T>], targets: &[Vec<T>]) -> Result<f64, &'static str> {
+        Ok(self.compute_metrics(inputs, targets, &[Metric::Accuracy])?[0].1)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::activation::{ReLU, Sigmoid};
+    use crate::activation::{Linear, ReLU, Sigmoid};
+    use crate::divergence::{DivergencePolicy, NonFiniteSource, TrainingError};
+    use crate::regularization::Regularizer;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts heap allocations made on the current thread, so tests running in
+    // parallel don't see each other's.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            System.alloc(layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            System.realloc(ptr, layout, new_size)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
 
     #[test]
     fn test_neural_network_creation() {
@@ -208,4 +755,327 @@ mod tests {
         
         assert!((output[0] - 0.7310585786300049).abs() < 1e-10);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_regularization_penalty() {
+        let mut nn = NeuralNetwork::new(0.1);
+        nn.add_input_layer(2, 2, Arc::new(ReLU) as Arc<dyn ActivationFunction>).unwrap();
+        nn.add_layer(1, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        assert_eq!(nn.regularization_penalty(), 0.0);
+
+        let layer = nn.layer_mut(1).unwrap();
+        layer.weights.data = vec![1.0, -3.0];
+        layer.kernel_regularizer = Some(Regularizer::L1(0.01));
+        assert!((nn.regularization_penalty() - 0.04).abs() < 1e-12);
+        assert!(nn.layer_mut(2).is_none());
+    }
+
+    #[test]
+    fn test_gradient_clipping() {
+        let build = |clipping: Option<GradientClipping>| {
+            let mut nn = NeuralNetwork::new(1.0);
+            nn.add_input_layer(1, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+            nn.layers[0].weights.data = vec![0.0];
+            nn.layers[0].biases.data = vec![0.0];
+            nn.set_gradient_clipping(clipping).unwrap();
+            nn.train(&[3.0], &[4.0]).unwrap();
+            (nn.layers[0].weights.data[0], nn.layers[0].biases.data[0])
+        };
+
+        // Unclipped: error 4, weight gradient 12, bias gradient 4
+        assert_eq!(build(None), (12.0, 4.0));
+        assert_eq!(build(Some(GradientClipping::Value(1.0))), (1.0, 1.0));
+
+        let (w, b) = build(Some(GradientClipping::GlobalNorm(1.0)));
+        assert!(((w * w + b * b).sqrt() - 1.0).abs() < 1e-12);
+        assert!((w / b - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_gradient_clipping_is_rejected() {
+        let mut nn = NeuralNetwork::new(1.0);
+        for clipping in [GradientClipping::Value(-1.0), GradientClipping::Norm(0.0), GradientClipping::GlobalNorm(f64::NAN), GradientClipping::Value(f64::INFINITY)] {
+            assert!(nn.set_gradient_clipping(Some(clipping)).is_err());
+        }
+        assert_eq!(nn.gradient_clipping, None);
+        assert!(nn.set_gradient_clipping(Some(GradientClipping::Value(1.0))).is_ok());
+    }
+
+    fn exploding_network() -> (NeuralNetwork, Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let mut nn = NeuralNetwork::new(10.0);
+        nn.add_input_layer(1, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+        nn.add_layer(1, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+        for layer in 0..2 {
+            nn.layers[layer].weights.data = vec![1.0];
+            nn.layers[layer].biases.data = vec![0.0];
+        }
+        (nn, vec![vec![100.0]], vec![vec![-100.0]])
+    }
+
+    #[test]
+    fn test_divergence_abort() {
+        let (mut nn, inputs, targets) = exploding_network();
+        nn.set_divergence_check(Some(DivergencePolicy::Abort));
+
+        match nn.fit(&inputs, &targets, 50, false) {
+            Err(TrainingError::Diverged(divergence)) => {
+                assert_eq!(divergence.sample, 0);
+                assert!(divergence.epoch > 1);
+                assert!(matches!(
+                    divergence.source,
+                    NonFiniteSource::Activation | NonFiniteSource::Gradient | NonFiniteSource::Weight
+                ));
+            }
+            other => panic!("expected divergence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_divergence_rollback() {
+        let (mut nn, inputs, targets) = exploding_network();
+        nn.set_divergence_check(Some(DivergencePolicy::Rollback));
+
+        nn.fit(&inputs, &targets, 50, false).unwrap();
+        let divergence = nn.last_divergence().expect("training should have diverged").clone();
+
+        for layer in nn.layers() {
+            assert!(layer.weights.is_finite());
+            assert!(layer.biases.is_finite());
+        }
+
+        // Without the check the same run ends with non-finite weights
+        let (mut unchecked, inputs, targets) = exploding_network();
+        unchecked.fit(&inputs, &targets, divergence.epoch, false).unwrap();
+        assert!(unchecked.layers().iter().any(|l| !l.weights.is_finite()));
+    }
+    
+    #[test]
+    fn test_multi_class_accuracy() {
+        let mut nn = NeuralNetwork::new(0.1);
+        nn.add_input_layer(2, 3, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+        nn.layers[0].weights.data = vec![1.0, 0.0, 0.0, 1.0, 0.8, 0.8];
+        nn.layers[0].biases.data = vec![0.0; 3];
+        
+        // No output reaches 0.5, so the old all-outputs threshold would score 0%
+        let inputs = vec![vec![0.4, 0.0], vec![0.0, 0.4], vec![0.2, 0.2]];
+        let targets = vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![0.0, 0.0, 1.0]];
+        assert_eq!(nn.calculate_accuracy(&inputs, &targets).unwrap(), 1.0);
+        
+        let results = nn.compute_metrics(&inputs, &targets, &[Metric::TopKAccuracy(1), Metric::MeanSquaredError]).unwrap();
+        assert_eq!(results[0], (Metric::TopKAccuracy(1), 1.0));
+        assert!(results[1].1 > 0.0);
+        
+        let wide_targets = vec![vec![0.0, 0.0, 0.0, 1.0]; 3];
+        assert!(nn.compute_metrics(&inputs, &wide_targets, &[Metric::TopKAccuracy(1)]).is_err());
+        assert!(nn.calculate_accuracy(&inputs, &wide_targets).is_err());
+    }
+    
+    #[test]
+    fn test_evaluate_matches_predict() {
+        let mut nn = NeuralNetwork::new(0.1);
+        nn.add_input_layer(3, 5, Arc::new(ReLU) as Arc<dyn ActivationFunction>).unwrap();
+        nn.add_layer(2, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn.set_metrics(vec![Metric::Accuracy, Metric::MeanAbsoluteError]);
+        
+        // More samples than one batch so the parallel path is exercised
+        let inputs: Vec<Vec<f64>> = (0..150).map(|i| vec![(i % 7) as f64 / 7.0, (i % 3) as f64 - 1.0, 0.5]).collect();
+        let targets: Vec<Vec<f64>> = (0..150).map(|i| if i % 2 == 0 { vec![1.0, 0.0] } else { vec![0.0, 1.0] }).collect();
+        
+        let report = nn.evaluate(&inputs, &targets).unwrap();
+        
+        let outputs: Vec<Vec<f64>> = inputs.iter().map(|x| nn.predict(x).unwrap()).collect();
+        let expected_loss = metrics::mean_squared_error(&outputs, &targets);
+        assert_eq!(report.samples, 150);
+        assert!((report.loss - expected_loss).abs() < 1e-12);
+        assert!((report.metrics[0].1 - metrics::accuracy(&outputs, &targets)).abs() < 1e-12);
+        assert!((report.metrics[1].1 - metrics::mean_absolute_error(&outputs, &targets)).abs() < 1e-12);
+        assert!(report.to_string().starts_with("150 samples - Loss: "));
+        
+        assert!(nn.evaluate(&[vec![1.0, 2.0]], &[vec![1.0, 0.0]]).is_err());
+    }
+    
+    #[test]
+    fn test_configured_loss() {
+        use crate::loss::BinaryCrossEntropy;
+        
+        let mut nn = NeuralNetwork::new(0.5);
+        nn.add_input_layer(1, 1, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn.set_loss(Some(Arc::new(BinaryCrossEntropy)));
+        
+        let inputs = vec![vec![-1.0], vec![1.0]];
+        let targets = vec![vec![0.0], vec![1.0]];
+        let before = nn.evaluate(&inputs, &targets).unwrap().loss;
+        nn.fit(&inputs, &targets, 200, false).unwrap();
+        let after = nn.evaluate(&inputs, &targets).unwrap().loss;
+        
+        assert!(after < before);
+        assert!(after < 0.2);
+    }
+    
+    fn checkpoint_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("nn-checkpoint-{}-{}", name, std::process::id()))
+    }
+    
+    fn checkpoint_data() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let inputs: Vec<Vec<f64>> = (0..24).map(|i| vec![(i % 6) as f64 / 6.0, (i / 6) as f64 / 4.0]).collect();
+        let targets = inputs.iter().map(|x| vec![if x[0] + x[1] > 0.7 { 1.0 } else { 0.0 }]).collect();
+        (inputs, targets)
+    }
+    
+    fn checkpoint_network() -> NeuralNetwork {
+        let mut nn = NeuralNetwork::new(0.3);
+        nn.add_input_layer(2, 4, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn.add_layer(1, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn
+    }
+    
+    #[test]
+    fn test_resume_from_checkpoint_is_exact() {
+        use crate::checkpoint::{Checkpoint, CheckpointPolicy, Checkpointing};
+        
+        let dir = checkpoint_dir("resume");
+        let (inputs, targets) = checkpoint_data();
+        
+        let mut full = checkpoint_network();
+        let initial = full.checkpoint(0);
+        full.set_seed(7);
+        full.set_checkpointing(Some(Checkpointing::new(&dir, CheckpointPolicy::EveryEpochs(3))));
+        full.fit(&inputs, &targets, 8, false).unwrap();
+        assert!(dir.join("epoch-0006.ckpt").exists());
+        assert!(!dir.join("epoch-0008.ckpt").exists());
+        
+        // A fresh network with different weights and seed picks up at epoch 3
+        let mut resumed = checkpoint_network();
+        resumed.set_seed(1234);
+        resumed.restore_checkpoint(&Checkpoint::load(dir.join("epoch-0003.ckpt")).unwrap()).unwrap();
+        resumed.fit(&inputs, &targets, 8, false).unwrap();
+        
+        for (a, b) in full.layers().iter().zip(resumed.layers()) {
+            assert_eq!(a.weights.data, b.weights.data);
+            assert_eq!(a.biases.data, b.biases.data);
+        }
+        assert!(resumed.restore_checkpoint(&initial).is_ok());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_best_validation_checkpoint() {
+        use crate::checkpoint::{Checkpoint, CheckpointPolicy, Checkpointing};
+        
+        let dir = checkpoint_dir("best");
+        let (inputs, targets) = checkpoint_data();
+        
+        let mut nn = checkpoint_network();
+        nn.set_checkpointing(Some(Checkpointing::new(&dir, CheckpointPolicy::BestValidation)));
+        assert!(nn.fit(&inputs, &targets, 5, false).is_err());
+        
+        nn.set_validation_data(&inputs, &targets);
+        nn.fit(&inputs, &targets, 5, false).unwrap();
+        
+        let best = Checkpoint::load(dir.join("best.ckpt")).unwrap();
+        let best_loss = best.best_validation_loss.unwrap();
+        assert!(best.epoch >= 1 && best.epoch <= 5);
+        
+        // The stored loss is what the checkpointed weights score on the validation set
+        let mut restored = checkpoint_network();
+        restored.restore_checkpoint(&best).unwrap();
+        assert!((restored.evaluate(&inputs, &targets).unwrap().loss - best_loss).abs() < 1e-12);
+        
+        let mut wrong_shape = NeuralNetwork::new(0.1);
+        wrong_shape.add_input_layer(3, 4, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        assert!(wrong_shape.restore_checkpoint(&best).is_err());
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_execution_context_matches_global_pool() {
+        use crate::execution::{ExecutionContext, Thresholds};
+
+        let (inputs, targets) = checkpoint_data();
+        let mut global = checkpoint_network();
+        let mut isolated = checkpoint_network();
+        isolated.restore_checkpoint(&global.checkpoint(0)).unwrap();
+        global.set_seed(3);
+        isolated.set_seed(3);
+        isolated.set_execution_context(Some(ExecutionContext::new(2, Thresholds::uniform(0)).unwrap()));
+
+        global.fit(&inputs, &targets, 4, false).unwrap();
+        isolated.fit(&inputs, &targets, 4, false).unwrap();
+        isolated.train(&inputs[0], &targets[0]).unwrap();
+        global.train(&inputs[0], &targets[0]).unwrap();
+
+        for (a, b) in global.layers().iter().zip(isolated.layers()) {
+            assert_eq!(a.weights.data, b.weights.data);
+            assert_eq!(a.biases.data, b.biases.data);
+        }
+        assert_eq!(global.predict(&inputs[1]).unwrap(), isolated.predict(&inputs[1]).unwrap());
+        // The parallel loss sum associates differently on pools of different sizes
+        let loss = global.evaluate(&inputs, &targets).unwrap().loss;
+        assert!((loss - isolated.evaluate(&inputs, &targets).unwrap().loss).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_f32_network_tracks_f64() {
+        let mut nn64 = NeuralNetwork::new(0.5);
+        nn64.add_input_layer(2, 3, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn64.add_layer(1, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        
+        let mut nn32 = NeuralNetwork::<f32>::with_learning_rate(0.5);
+        nn32.add_input_layer(2, 3, Arc::new(Sigmoid)).unwrap();
+        nn32.add_layer(1, Arc::new(Sigmoid)).unwrap();
+        nn32.restore_checkpoint(&nn64.checkpoint(0)).unwrap();
+        
+        let inputs = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+        let targets = vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]];
+        let inputs32: Vec<Vec<f32>> = inputs.iter().map(|r| r.iter().map(|&v| v as f32).collect()).collect();
+        let targets32: Vec<Vec<f32>> = targets.iter().map(|r| r.iter().map(|&v| v as f32).collect()).collect();
+        
+        for _ in 0..50 {
+            for i in 0..inputs.len() {
+                nn64.train(&inputs[i], &targets[i]).unwrap();
+                nn32.train(&inputs32[i], &targets32[i]).unwrap();
+            }
+        }
+        
+        for (input, input32) in inputs.iter().zip(&inputs32) {
+            let expected = nn64.predict(input).unwrap()[0];
+            let actual = nn32.predict(input32).unwrap()[0] as f64;
+            assert!((expected - actual).abs() < 1e-4, "{} vs {}", expected, actual);
+        }
+        let loss64 = nn64.evaluate(&inputs, &targets).unwrap().loss;
+        let loss32 = nn32.evaluate(&inputs32, &targets32).unwrap().loss;
+        assert!((loss64 - loss32).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_steady_state_training_does_not_allocate() {
+        use crate::loss::{BinaryCrossEntropy, MeanSquaredError};
+        
+        let losses: [Option<Arc<dyn LossFunction>>; 3] = [None, Some(Arc::new(BinaryCrossEntropy)), Some(Arc::new(MeanSquaredError))];
+        let clippings = [None, Some(GradientClipping::Norm(0.5)), Some(GradientClipping::GlobalNorm(0.5))];
+        for (loss, clipping) in losses.into_iter().zip(clippings) {
+            let mut nn = NeuralNetwork::new(0.1);
+            nn.add_input_layer(2, 4, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+            nn.add_layer(1, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+            nn.set_loss(loss);
+            nn.set_gradient_clipping(clipping).unwrap();
+            
+            let samples = [([0.0, 1.0], [1.0]), ([1.0, 1.0], [0.0])];
+            
+            // The first steps size the buffers
+            for (input, target) in &samples {
+                nn.train(input, target).unwrap();
+            }
+            
+            let before = ALLOCATIONS.with(Cell::get);
+            for _ in 0..10 {
+                for (input, target) in &samples {
+                    nn.train(input, target).unwrap();
+                }
+            }
+            assert_eq!(ALLOCATIONS.with(Cell::get) - before, 0);
+        }
+    }
+}
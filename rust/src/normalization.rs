@@ -0,0 +1,130 @@
+use crate::matrix::Matrix;
+
+// Normalizes each column (one token or sample) across its features, then
+// applies a learned per-feature scale and shift.
+pub struct LayerNorm {
+    pub size: usize,
+    pub epsilon: f64,
+    pub gamma: Matrix,
+    pub beta: Matrix,
+    last_normalized: Option<Matrix>,
+    last_inv_std: Vec<f64>,
+}
+
+impl LayerNorm {
+    pub fn new(size: usize) -> Self {
+        LayerNorm {
+            size,
+            epsilon: 1e-5,
+            gamma: Matrix::from_array(&vec![1.0; size]),
+            beta: Matrix::new(size, 1),
+            last_normalized: None,
+            last_inv_std: Vec::new(),
+        }
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.rows != self.size {
+            return Err("Input size does not match layer normalization");
+        }
+
+        let (rows, cols) = (input.rows, input.cols);
+        let mut normalized = Matrix::new(rows, cols);
+        let mut output = Matrix::new(rows, cols);
+        self.last_inv_std = vec![0.0; cols];
+
+        for t in 0..cols {
+            let mean = (0..rows).map(|i| input.data[i * cols + t]).sum::<f64>() / rows as f64;
+            let variance = (0..rows)
+                .map(|i| (input.data[i * cols + t] - mean).powi(2))
+                .sum::<f64>() / rows as f64;
+            let inv_std = 1.0 / (variance + self.epsilon).sqrt();
+            self.last_inv_std[t] = inv_std;
+
+            for i in 0..rows {
+                let x_hat = (input.data[i * cols + t] - mean) * inv_std;
+                normalized.data[i * cols + t] = x_hat;
+                output.data[i * cols + t] = self.gamma.data[i] * x_hat + self.beta.data[i];
+            }
+        }
+
+        self.last_normalized = Some(normalized);
+        Ok(output)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.gamma, &mut self.beta]
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        let normalized = self.last_normalized.as_ref().ok_or("No input stored for backpropagation")?;
+        normalized.check_size_match(output_error)?;
+
+        let (rows, cols) = (normalized.rows, normalized.cols);
+        let mut input_error = Matrix::new(rows, cols);
+        let mut gamma_gradient = vec![0.0; rows];
+        let mut beta_gradient = vec![0.0; rows];
+
+        for t in 0..cols {
+            let mut mean_error = 0.0;
+            let mut mean_error_x_hat = 0.0;
+            for i in 0..rows {
+                let idx = i * cols + t;
+                let e = output_error.data[idx] * self.gamma.data[i];
+                mean_error += e;
+                mean_error_x_hat += e * normalized.data[idx];
+                gamma_gradient[i] += output_error.data[idx] * normalized.data[idx];
+                beta_gradient[i] += output_error.data[idx];
+            }
+            mean_error /= rows as f64;
+            mean_error_x_hat /= rows as f64;
+
+            for i in 0..rows {
+                let idx = i * cols + t;
+                let e = output_error.data[idx] * self.gamma.data[i];
+                input_error.data[idx] =
+                    self.last_inv_std[t] * (e - mean_error - normalized.data[idx] * mean_error_x_hat);
+            }
+        }
+
+        for i in 0..rows {
+            self.gamma.data[i] += learning_rate * gamma_gradient[i];
+            self.beta.data[i] += learning_rate * beta_gradient[i];
+        }
+
+        Ok(input_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck;
+
+    #[test]
+    fn test_layer_norm_normalizes_columns() {
+        let mut norm = LayerNorm::new(3);
+        let input = Matrix { rows: 3, cols: 2, data: vec![1.0, 10.0, 2.0, 20.0, 3.0, 60.0] };
+        let output = norm.feed_forward(&input).unwrap();
+
+        for t in 0..2 {
+            let column: Vec<f64> = (0..3).map(|i| output.get(i, t)).collect();
+            let mean = column.iter().sum::<f64>() / 3.0;
+            let variance = column.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 3.0;
+            assert!(mean.abs() < 1e-10);
+            assert!((variance - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_layer_norm_gradients() {
+        let mut norm = LayerNorm::new(4);
+        norm.gamma.data = vec![0.5, 1.5, -1.0, 2.0];
+        norm.beta.data = vec![0.1, 0.0, -0.2, 0.3];
+        let input = Matrix { rows: 4, cols: 2, data: vec![0.3, -1.2, 0.8, 0.4, -0.5, 2.0, 1.1, 0.0] };
+
+        assert!(gradcheck::check_module(&mut norm, &input).unwrap().passed(1e-6));
+        assert!(gradcheck::check_module_parameters(&mut norm, &input, |n| &mut n.gamma).unwrap().passed(1e-5));
+        assert!(gradcheck::check_module_parameters(&mut norm, &input, |n| &mut n.beta).unwrap().passed(1e-5));
+    }
+}
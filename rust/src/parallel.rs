@@ -0,0 +1,447 @@
+use crate::clipping::GradientClipping;
+use crate::divergence::NonFiniteSource;
+use crate::float::Float;
+use crate::layer::Layer;
+use crate::loss::LossFunction;
+use crate::matrix::Matrix;
+use crate::neural_network::{backward_layers, forward_layers, sample_error, LossScratch, SampleOutcome};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// How `fit` turns samples into weight updates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrainingMode {
+    // One update per sample, in shuffled order.
+    #[default]
+    Serial,
+    // One update per mini-batch of `batch_size` shuffled samples, from the mean
+    // of their gradients. The batch is split into `shards` pieces that run on
+    // separate replicas of the network in parallel; 0 uses one shard per
+    // thread of the current pool. The result is the same for any shard count
+    // up to rounding, and a batch size of 1 reproduces `Serial`.
+    DataParallel { batch_size: usize, shards: usize },
+    // Lock-free asynchronous SGD after Niu et al.'s Hogwild!. Each epoch's
+    // shuffled samples are dealt out to `threads` workers (0 uses the current
+    // pool's size), which read the shared weights, compute one sample's
+    // gradient and add it back element by element, all without locks.
+    //
+    // The tradeoffs, which are the price of never waiting on other workers:
+    // - A worker's gradient may be computed from weights that other workers
+    //   have half-updated, and two workers adding to the same weight at once
+    //   can lose one of the updates. Each weight is read and written
+    //   atomically, so values are never torn, but nothing orders the updates.
+    // - Runs are not reproducible, even with a fixed seed and thread count.
+    // - It converges when updates rarely collide: sparse inputs, where each
+    //   sample's gradient touches few weights, and small learning rates. Dense
+    //   problems with many threads can converge more slowly than `Serial`.
+    // - Before every sample a worker reads the first layer's shared weights
+    //   only in the columns of the sample's non-zero inputs, and only writes
+    //   back non-zero gradient entries, so on sparse inputs both scale with
+    //   the non-zero count rather than the input width. Later layers, and a
+    //   first layer with a kernel regularizer, whose gradient touches every
+    //   weight, are read in full.
+    // - Weights are only checked and copied back at the end of each epoch, so
+    //   divergence checks are not supported in this mode.
+    Hogwild { threads: usize },
+}
+
+impl TrainingMode {
+    // Samples per update.
+    pub(crate) fn batch_size(&self) -> Result<usize, &'static str> {
+        match *self {
+            TrainingMode::Serial => Ok(1),
+            TrainingMode::DataParallel { batch_size: 0, .. } => Err("Batch size must be positive"),
+            TrainingMode::DataParallel { batch_size, .. } => Ok(batch_size),
+            // The whole epoch is handed to the workers at once
+            TrainingMode::Hogwild { .. } => Ok(usize::MAX),
+        }
+    }
+}
+
+// What a Hogwild worker needs, besides the data, to turn a sample into an update.
+#[derive(Clone, Copy)]
+pub(crate) struct HogwildStep<'a> {
+    pub loss: Option<&'a Arc<dyn LossFunction>>,
+    pub clipping: Option<GradientClipping>,
+    pub learning_rate: f64,
+}
+
+// A worker's copy of the network. Weights are synced from the network before
+// every batch; the buffers persist across batches.
+pub(crate) struct Replica<T: Float> {
+    layers: Vec<Layer<T>>,
+    input: Matrix<T>,
+    error: Matrix<T>,
+    loss: LossScratch,
+}
+
+impl<T: Float> Replica<T> {
+    pub(crate) fn new(layers: &[Layer<T>]) -> Self {
+        Replica {
+            layers: layers.iter().map(Layer::replica).collect(),
+            input: Matrix::zeros(0, 0),
+            error: Matrix::zeros(0, 0),
+            loss: LossScratch::default(),
+        }
+    }
+
+    // A replica that also carries the layers' regularizers, for workers that
+    // apply their own updates.
+    pub(crate) fn regularized(layers: &[Layer<T>]) -> Self {
+        let mut replica = Replica::new(layers);
+        for (copy, layer) in replica.layers.iter_mut().zip(layers) {
+            copy.kernel_regularizer = layer.kernel_regularizer;
+            copy.bias_regularizer = layer.bias_regularizer;
+        }
+        replica
+    }
+
+    pub(crate) fn sync(&mut self, layers: &[Layer<T>]) {
+        for (replica, layer) in self.layers.iter_mut().zip(layers) {
+            replica.sync_from(layer);
+        }
+    }
+
+    pub(crate) fn layers(&self) -> &[Layer<T>] {
+        &self.layers
+    }
+
+    // Leaves the gradients summed over the `shard` samples in the replica's
+    // layers, and returns the summed loss. The samples go through as one batch,
+    // one per column.
+    pub(crate) fn gradients(
+        &mut self,
+        inputs: &[Vec<T>],
+        targets: &[Vec<T>],
+        shard: &[usize],
+        loss: Option<&Arc<dyn LossFunction>>,
+        check: bool,
+    ) -> Result<SampleOutcome, &'static str> {
+        let input_size = self.layers.first().ok_or("Network has no layers")?.weights.cols;
+        let batch = shard.len();
+        self.input.resize(input_size, batch);
+        for (col, &i) in shard.iter().enumerate() {
+            if inputs[i].len() != input_size {
+                return Err("Input size does not match network");
+            }
+            for (row, &value) in inputs[i].iter().enumerate() {
+                self.input.data[row * batch + col] = value;
+            }
+        }
+
+        if let Some(i) = forward_layers(&mut self.layers, &self.input, check)? {
+            return Ok((f64::NAN, Some((NonFiniteSource::Activation, i))));
+        }
+
+        let output = match self.layers.last() {
+            Some(layer) => layer.output().ok_or("No activation stored for backpropagation")?,
+            None => &self.input,
+        };
+        self.error.resize(output.rows, batch);
+        let mut output_column = vec![T::ZERO; output.rows];
+        let mut error_column = vec![T::ZERO; output.rows];
+        let mut total_loss = 0.0;
+        for (col, &i) in shard.iter().enumerate() {
+            if targets[i].len() != output.rows {
+                return Err("Matrix dimensions don't match for subtraction");
+            }
+            for (row, value) in output_column.iter_mut().enumerate() {
+                *value = output.get(row, col);
+            }
+            total_loss += sample_error(loss, &output_column, &targets[i], &mut error_column, &mut self.loss);
+            for (row, &value) in error_column.iter().enumerate() {
+                self.error.set(row, col, value);
+            }
+        }
+
+        backward_layers(&mut self.layers, &self.error)?;
+        Ok((total_loss, None))
+    }
+
+    // A Hogwild worker's share of an epoch: every sample in `samples` is trained
+    // against the current `shared` weights, and the update written straight back.
+    // Returns the summed loss.
+    pub(crate) fn hogwild(
+        &mut self,
+        shared: &SharedWeights,
+        inputs: &[Vec<T>],
+        targets: &[Vec<T>],
+        samples: &[usize],
+        step: HogwildStep,
+    ) -> Result<f64, &'static str> {
+        let mut total_loss = 0.0;
+        for &i in samples {
+            shared.load_for(&mut self.layers, &inputs[i]);
+            total_loss += self.gradients(inputs, targets, &[i], step.loss, false)?.0;
+            if let Some(clipping) = step.clipping {
+                clipping.apply_to_layers(&mut self.layers);
+            }
+            shared.add(&mut self.layers, T::from_f64(step.learning_rate));
+        }
+        Ok(total_loss)
+    }
+}
+
+// The network's weights and biases as atomics, for lock-free updates from
+// several threads. Floats are stored by their bit patterns.
+pub(crate) struct SharedWeights {
+    // Weights then biases, per layer.
+    layers: Vec<[Vec<AtomicU64>; 2]>,
+}
+
+impl SharedWeights {
+    pub(crate) fn new<T: Float>(layers: &[Layer<T>]) -> Self {
+        let cells = |m: &Matrix<T>| m.data.iter().map(|v| AtomicU64::new(v.to_bits())).collect();
+        SharedWeights {
+            layers: layers.iter().map(|l| [cells(&l.weights), cells(&l.biases)]).collect(),
+        }
+    }
+
+    pub(crate) fn load_into<T: Float>(&self, layers: &mut [Layer<T>]) {
+        self.load_from(0, layers);
+    }
+
+    // What one sample's step reads. First-layer weights in the columns of zero
+    // inputs are multiplied by zero going forward and get a zero gradient going
+    // back, so they are left stale, unless a kernel regularizer needs them.
+    pub(crate) fn load_for<T: Float>(&self, layers: &mut [Layer<T>], input: &[T]) {
+        let Some((first, rest)) = layers.split_first_mut() else {
+            return;
+        };
+        let [weights, biases] = &self.layers[0];
+        if first.kernel_regularizer.is_some() || input.len() != first.weights.cols {
+            load(&mut first.weights.data, weights);
+        } else {
+            let cols = first.weights.cols;
+            for (col, _) in input.iter().enumerate().filter(|&(_, &x)| x != T::ZERO) {
+                for row in 0..first.weights.rows {
+                    let index = row * cols + col;
+                    first.weights.data[index] = T::from_bits(weights[index].load(Ordering::Relaxed));
+                }
+            }
+        }
+        load(&mut first.biases.data, biases);
+        self.load_from(1, rest);
+    }
+
+    // Fills `layers` from the shared layers starting at `start`.
+    fn load_from<T: Float>(&self, start: usize, layers: &mut [Layer<T>]) {
+        for (layer, [weights, biases]) in layers.iter_mut().zip(&self.layers[start..]) {
+            load(&mut layer.weights.data, weights);
+            load(&mut layer.biases.data, biases);
+        }
+    }
+
+    // `w += learning_rate * g` for every non-zero entry of the gradients pending
+    // in `layers`. The load and store are separate, so a concurrent update to
+    // the same weight in between is overwritten; that is the Hogwild bargain.
+    fn add<T: Float>(&self, layers: &mut [Layer<T>], learning_rate: T) {
+        for (layer, cells) in layers.iter_mut().zip(&self.layers) {
+            let Some((weights, biases)) = layer.gradients_mut() else {
+                continue;
+            };
+            for (gradient, cells) in [weights, biases].into_iter().zip(cells) {
+                for (&g, cell) in gradient.data.iter().zip(cells) {
+                    if g != T::ZERO {
+                        let value = T::from_bits(cell.load(Ordering::Relaxed));
+                        cell.store((value + learning_rate * g).to_bits(), Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn load<T: Float>(values: &mut [T], cells: &[AtomicU64]) {
+    for (value, cell) in values.iter_mut().zip(cells) {
+        *value = T::from_bits(cell.load(Ordering::Relaxed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activation::{ActivationFunction, Sigmoid, Tanh};
+    use crate::execution::{ExecutionContext, Thresholds};
+    use crate::neural_network::NeuralNetwork;
+
+    fn network() -> NeuralNetwork {
+        let mut nn = NeuralNetwork::new(0.3);
+        nn.add_input_layer(3, 5, Arc::new(Tanh) as Arc<dyn ActivationFunction>).unwrap();
+        nn.add_layer(2, Arc::new(Sigmoid) as Arc<dyn ActivationFunction>).unwrap();
+        nn.set_seed(11);
+        nn
+    }
+
+    // Eight samples, few enough that `fit` keeps them in order.
+    fn data() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let inputs: Vec<Vec<f64>> = (0..8)
+            .map(|i| vec![(i % 2) as f64, (i / 2 % 2) as f64, i as f64 / 8.0 - 0.4])
+            .collect();
+        let targets = inputs.iter().map(|x| vec![(x[0] + x[1]) / 2.0, x[2].abs()]).collect();
+        (inputs, targets)
+    }
+
+    fn assert_same_weights(a: &NeuralNetwork, b: &NeuralNetwork, tolerance: f64) {
+        for (x, y) in a.layers().iter().zip(b.layers()) {
+            for (p, q) in x.weights.data.iter().chain(&x.biases.data).zip(y.weights.data.iter().chain(&y.biases.data)) {
+                assert!((p - q).abs() < tolerance, "{} vs {}", p, q);
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_of_one_matches_serial() {
+        let (inputs, targets) = data();
+        let mut serial = network();
+        let mut parallel = network();
+        parallel.restore_checkpoint(&serial.checkpoint(0)).unwrap();
+        parallel.set_training_mode(TrainingMode::DataParallel { batch_size: 1, shards: 0 });
+
+        serial.fit(&inputs, &targets, 5, false).unwrap();
+        parallel.fit(&inputs, &targets, 5, false).unwrap();
+        assert_same_weights(&serial, &parallel, 1e-12);
+    }
+
+    #[test]
+    fn test_mini_batch_matches_serial_averaging() {
+        let (inputs, targets) = data();
+        let mut reference = network();
+        let initial = reference.checkpoint(0);
+
+        // Mean gradient per batch of three, accumulated one sample at a time
+        for _ in 0..4 {
+            for batch in (0..inputs.len()).collect::<Vec<_>>().chunks(3) {
+                let mut sums: Vec<(Matrix, Matrix)> = reference.layers().iter()
+                    .map(|l| (Matrix::new(l.weights.rows, l.weights.cols), Matrix::new(l.biases.rows, 1)))
+                    .collect();
+                for &i in batch {
+                    let mut activation = Matrix::from_array(&inputs[i]);
+                    for l in 0..sums.len() {
+                        activation = reference.layer_mut(l).unwrap().feed_forward(&activation).unwrap();
+                    }
+                    let mut error = Matrix::from_array(&targets[i]).subtract(&activation).unwrap();
+                    for l in (0..sums.len()).rev() {
+                        let layer = reference.layer_mut(l).unwrap();
+                        error = layer.compute_gradients(&error).unwrap();
+                        let (w, b) = layer.gradients_mut().unwrap();
+                        sums[l].0.add_assign(w).unwrap();
+                        sums[l].1.add_assign(b).unwrap();
+                    }
+                }
+                for (l, (w, b)) in sums.iter().enumerate() {
+                    let layer = reference.layer_mut(l).unwrap();
+                    layer.weights.add_assign(&w.multiply(0.3 / batch.len() as f64)).unwrap();
+                    layer.biases.add_assign(&b.multiply(0.3 / batch.len() as f64)).unwrap();
+                }
+            }
+        }
+
+        for (threads, shards) in [(1, 1), (2, 2), (4, 3), (2, 0)] {
+            let mut nn = network();
+            nn.restore_checkpoint(&initial).unwrap();
+            nn.set_training_mode(TrainingMode::DataParallel { batch_size: 3, shards });
+            nn.set_execution_context(Some(ExecutionContext::new(threads, Thresholds::default()).unwrap()));
+            nn.fit(&inputs, &targets, 4, false).unwrap();
+            assert_same_weights(&reference, &nn, 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_invalid_batch_size() {
+        let (inputs, targets) = data();
+        let mut nn = network();
+        nn.set_training_mode(TrainingMode::DataParallel { batch_size: 0, shards: 2 });
+        assert!(nn.fit(&inputs, &targets, 1, false).is_err());
+
+        let mut bad_inputs = inputs.clone();
+        bad_inputs[5].pop();
+        nn.set_training_mode(TrainingMode::DataParallel { batch_size: 4, shards: 2 });
+        assert!(nn.fit(&bad_inputs, &targets, 1, false).is_err());
+    }
+
+    #[test]
+    fn test_hogwild_converges_like_serial() {
+        use crate::activation::Linear;
+        use rand::{Rng, SeedableRng};
+        use rand_chacha::ChaCha8Rng;
+
+        // Sparse regression: each sample sets 2 of 40 features
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let coefficients: Vec<f64> = (0..40).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let mut inputs = Vec::new();
+        let mut targets = Vec::new();
+        for _ in 0..400 {
+            let mut x = vec![0.0; 40];
+            for _ in 0..2 {
+                x[rng.gen_range(0..40)] = rng.gen_range(0.5..1.5);
+            }
+            targets.push(vec![x.iter().zip(&coefficients).map(|(a, b)| a * b).sum::<f64>()]);
+            inputs.push(x);
+        }
+
+        let mut serial = NeuralNetwork::new(0.05);
+        serial.add_input_layer(40, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+        serial.set_seed(2);
+        let mut hogwild = NeuralNetwork::new(0.05);
+        hogwild.add_input_layer(40, 1, Arc::new(Linear) as Arc<dyn ActivationFunction>).unwrap();
+        hogwild.restore_checkpoint(&serial.checkpoint(0)).unwrap();
+        hogwild.set_seed(2);
+        hogwild.set_training_mode(TrainingMode::Hogwild { threads: 4 });
+        hogwild.set_execution_context(Some(ExecutionContext::new(4, Thresholds::default()).unwrap()));
+
+        let initial = serial.evaluate(&inputs, &targets).unwrap().loss;
+        serial.fit(&inputs, &targets, 30, false).unwrap();
+        hogwild.fit(&inputs, &targets, 30, false).unwrap();
+        let serial_loss = serial.evaluate(&inputs, &targets).unwrap().loss;
+        let hogwild_loss = hogwild.evaluate(&inputs, &targets).unwrap().loss;
+
+        assert!(serial_loss < initial * 0.01, "{} from {}", serial_loss, initial);
+        assert!(hogwild_loss < initial * 0.01, "{} from {}", hogwild_loss, initial);
+        assert!(hogwild_loss < serial_loss * 2.0 + 1e-4, "{} vs {}", hogwild_loss, serial_loss);
+    }
+
+    #[test]
+    fn test_hogwild_reads_only_touched_first_layer_weights() {
+        use crate::regularization::Regularizer;
+
+        let shared_net = network();
+        let shared = SharedWeights::new(shared_net.layers());
+        let mut layers: Vec<Layer> = shared_net.layers().iter().map(Layer::replica).collect();
+        for layer in &mut layers {
+            layer.weights.data.iter_mut().for_each(|w| *w = 9.0);
+            layer.biases.data.iter_mut().for_each(|b| *b = 9.0);
+        }
+
+        shared.load_for(&mut layers, &[0.0, 0.5, 0.0]);
+        let (expected, loaded) = (&shared_net.layers()[0].weights, &layers[0].weights);
+        for row in 0..loaded.rows {
+            assert_eq!(loaded.get(row, 0), 9.0);
+            assert_eq!(loaded.get(row, 1), expected.get(row, 1));
+            assert_eq!(loaded.get(row, 2), 9.0);
+        }
+        assert_eq!(layers[0].biases, shared_net.layers()[0].biases);
+        assert_eq!(layers[1].weights, shared_net.layers()[1].weights);
+
+        // A regularized first layer has gradients on every weight, so it is read in full
+        layers[0].kernel_regularizer = Some(Regularizer::L2(0.1));
+        shared.load_for(&mut layers, &[0.0, 0.5, 0.0]);
+        assert_eq!(layers[0].weights, shared_net.layers()[0].weights);
+    }
+
+    #[test]
+    fn test_hogwild_rejects_divergence_checks() {
+        use crate::divergence::DivergencePolicy;
+
+        let (inputs, targets) = data();
+        let mut nn = network();
+        nn.set_training_mode(TrainingMode::Hogwild { threads: 2 });
+        nn.set_divergence_check(Some(DivergencePolicy::Abort));
+        assert!(nn.fit(&inputs, &targets, 1, false).is_err());
+
+        nn.set_divergence_check(None);
+        let before = nn.evaluate(&inputs, &targets).unwrap().loss;
+        nn.fit(&inputs, &targets, 20, false).unwrap();
+        assert!(nn.evaluate(&inputs, &targets).unwrap().loss < before);
+    }
+}
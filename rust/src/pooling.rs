@@ -0,0 +1,351 @@
+use crate::matrix::Matrix;
+
+// Spatial layers take feature maps laid out as a `channels x (height * width)`
+// matrix, with each row holding one channel in row-major order.
+
+fn output_dims(
+    input_height: usize,
+    input_width: usize,
+    pool_size: (usize, usize),
+    stride: (usize, usize),
+) -> Result<(usize, usize), &'static str> {
+    if pool_size.0 == 0 || pool_size.1 == 0 || stride.0 == 0 || stride.1 == 0 {
+        return Err("Pool size and stride must be non-zero");
+    }
+    if pool_size.0 > input_height || pool_size.1 > input_width {
+        return Err("Pool size larger than input");
+    }
+    let out_height = (input_height - pool_size.0) / stride.0 + 1;
+    let out_width = (input_width - pool_size.1) / stride.1 + 1;
+    Ok((out_height, out_width))
+}
+
+pub struct MaxPool2D {
+    pub input_height: usize,
+    pub input_width: usize,
+    pub pool_size: (usize, usize),
+    pub stride: (usize, usize),
+    input_shape: Option<(usize, usize)>,
+    argmax: Vec<usize>,
+}
+
+impl MaxPool2D {
+    pub fn new(input_height: usize, input_width: usize, pool_size: (usize, usize), stride: (usize, usize)) -> Self {
+        MaxPool2D {
+            input_height,
+            input_width,
+            pool_size,
+            stride,
+            input_shape: None,
+            argmax: Vec::new(),
+        }
+    }
+
+    pub fn output_dims(&self) -> Result<(usize, usize), &'static str> {
+        output_dims(self.input_height, self.input_width, self.pool_size, self.stride)
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.cols != self.input_height * self.input_width {
+            return Err("Input size does not match pooling dimensions");
+        }
+        let (out_height, out_width) = self.output_dims()?;
+        let out_size = out_height * out_width;
+
+        let mut output = Matrix::new(input.rows, out_size);
+        self.argmax = vec![0; input.rows * out_size];
+
+        for c in 0..input.rows {
+            let channel = &input.data[c * input.cols..(c + 1) * input.cols];
+            for oy in 0..out_height {
+                for ox in 0..out_width {
+                    let mut best_idx = (oy * self.stride.0) * self.input_width + ox * self.stride.1;
+                    let mut best = channel[best_idx];
+
+                    for py in 0..self.pool_size.0 {
+                        for px in 0..self.pool_size.1 {
+                            let idx = (oy * self.stride.0 + py) * self.input_width + ox * self.stride.1 + px;
+                            if channel[idx] > best {
+                                best = channel[idx];
+                                best_idx = idx;
+                            }
+                        }
+                    }
+
+                    let out_idx = c * out_size + oy * out_width + ox;
+                    output.data[out_idx] = best;
+                    self.argmax[out_idx] = best_idx;
+                }
+            }
+        }
+
+        self.input_shape = Some((input.rows, input.cols));
+        Ok(output)
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, _learning_rate: f64) -> Result<Matrix, &'static str> {
+        let (rows, cols) = self.input_shape.ok_or("No input stored for backpropagation")?;
+        if output_error.rows != rows || output_error.data.len() != self.argmax.len() {
+            return Err("Matrix size mismatch");
+        }
+
+        // Only the input that won each window receives its gradient
+        let mut input_error = Matrix::new(rows, cols);
+        let out_size = output_error.cols;
+        for (out_idx, &err) in output_error.data.iter().enumerate() {
+            let c = out_idx / out_size;
+            input_error.data[c * cols + self.argmax[out_idx]] += err;
+        }
+
+        Ok(input_error)
+    }
+}
+
+pub struct AvgPool2D {
+    pub input_height: usize,
+    pub input_width: usize,
+    pub pool_size: (usize, usize),
+    pub stride: (usize, usize),
+    input_shape: Option<(usize, usize)>,
+}
+
+impl AvgPool2D {
+    pub fn new(input_height: usize, input_width: usize, pool_size: (usize, usize), stride: (usize, usize)) -> Self {
+        AvgPool2D {
+            input_height,
+            input_width,
+            pool_size,
+            stride,
+            input_shape: None,
+        }
+    }
+
+    pub fn output_dims(&self) -> Result<(usize, usize), &'static str> {
+        output_dims(self.input_height, self.input_width, self.pool_size, self.stride)
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.cols != self.input_height * self.input_width {
+            return Err("Input size does not match pooling dimensions");
+        }
+        let (out_height, out_width) = self.output_dims()?;
+        let out_size = out_height * out_width;
+        let window = (self.pool_size.0 * self.pool_size.1) as f64;
+
+        let mut output = Matrix::new(input.rows, out_size);
+
+        for c in 0..input.rows {
+            let channel = &input.data[c * input.cols..(c + 1) * input.cols];
+            for oy in 0..out_height {
+                for ox in 0..out_width {
+                    let mut sum = 0.0;
+                    for py in 0..self.pool_size.0 {
+                        for px in 0..self.pool_size.1 {
+                            sum += channel[(oy * self.stride.0 + py) * self.input_width + ox * self.stride.1 + px];
+                        }
+                    }
+                    output.data[c * out_size + oy * out_width + ox] = sum / window;
+                }
+            }
+        }
+
+        self.input_shape = Some((input.rows, input.cols));
+        Ok(output)
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, _learning_rate: f64) -> Result<Matrix, &'static str> {
+        let (rows, cols) = self.input_shape.ok_or("No input stored for backpropagation")?;
+        let (out_height, out_width) = self.output_dims()?;
+        if output_error.rows != rows || output_error.cols != out_height * out_width {
+            return Err("Matrix size mismatch");
+        }
+        let window = (self.pool_size.0 * self.pool_size.1) as f64;
+
+        let mut input_error = Matrix::new(rows, cols);
+        for c in 0..rows {
+            for oy in 0..out_height {
+                for ox in 0..out_width {
+                    let share = output_error.data[c * output_error.cols + oy * out_width + ox] / window;
+                    for py in 0..self.pool_size.0 {
+                        for px in 0..self.pool_size.1 {
+                            let idx = (oy * self.stride.0 + py) * self.input_width + ox * self.stride.1 + px;
+                            input_error.data[c * cols + idx] += share;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(input_error)
+    }
+}
+
+#[derive(Default)]
+pub struct GlobalAveragePool {
+    input_shape: Option<(usize, usize)>,
+}
+
+impl GlobalAveragePool {
+    pub fn new() -> Self {
+        GlobalAveragePool { input_shape: None }
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.cols == 0 {
+            return Err("Cannot pool an empty input");
+        }
+
+        let mut output = Matrix::new(input.rows, 1);
+        for c in 0..input.rows {
+            let sum: f64 = input.data[c * input.cols..(c + 1) * input.cols].iter().sum();
+            output.data[c] = sum / input.cols as f64;
+        }
+
+        self.input_shape = Some((input.rows, input.cols));
+        Ok(output)
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, _learning_rate: f64) -> Result<Matrix, &'static str> {
+        let (rows, cols) = self.input_shape.ok_or("No input stored for backpropagation")?;
+        if output_error.rows != rows || output_error.cols != 1 {
+            return Err("Matrix size mismatch");
+        }
+
+        let mut input_error = Matrix::new(rows, cols);
+        for c in 0..rows {
+            let share = output_error.data[c] / cols as f64;
+            input_error.data[c * cols..(c + 1) * cols].fill(share);
+        }
+
+        Ok(input_error)
+    }
+}
+
+// Turns a feature map into the column vector expected by a dense `Layer`.
+#[derive(Default)]
+pub struct Flatten {
+    input_shape: Option<(usize, usize)>,
+}
+
+impl Flatten {
+    pub fn new() -> Self {
+        Flatten { input_shape: None }
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        self.input_shape = Some((input.rows, input.cols));
+        Ok(Matrix::from_array(&input.data))
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, _learning_rate: f64) -> Result<Matrix, &'static str> {
+        let (rows, cols) = self.input_shape.ok_or("No input stored for backpropagation")?;
+        if output_error.data.len() != rows * cols {
+            return Err("Matrix size mismatch");
+        }
+
+        Ok(Matrix {
+            rows,
+            cols,
+            data: output_error.data.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature_map(channels: usize, values: &[f64]) -> Matrix {
+        Matrix {
+            rows: channels,
+            cols: values.len() / channels,
+            data: values.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_max_pool_forward_and_backward() {
+        let input = feature_map(1, &[
+            1.0, 2.0, 5.0, 0.0,
+            3.0, 4.0, 1.0, 1.0,
+            0.0, 1.0, 2.0, 8.0,
+            1.0, 0.0, 3.0, 1.0,
+        ]);
+        let mut pool = MaxPool2D::new(4, 4, (2, 2), (2, 2));
+        let output = pool.feed_forward(&input).unwrap();
+        assert_eq!(output.data, vec![4.0, 5.0, 1.0, 8.0]);
+
+        let error = feature_map(1, &[1.0, 2.0, 3.0, 4.0]);
+        let input_error = pool.backpropagate(&error, 0.1).unwrap();
+        assert_eq!(input_error.get(0, 5), 1.0);
+        assert_eq!(input_error.get(0, 2), 2.0);
+        assert_eq!(input_error.get(0, 9), 3.0);
+        assert_eq!(input_error.get(0, 11), 4.0);
+        assert_eq!(input_error.data.iter().sum::<f64>(), 10.0);
+    }
+
+    #[test]
+    fn test_max_pool_overlapping_stride() {
+        let input = feature_map(2, &[
+            1.0, 2.0, 3.0,
+            4.0, 5.0, 6.0,
+            7.0, 8.0, 9.0,
+            0.0, 0.0, 0.0,
+            0.0, 9.0, 0.0,
+            0.0, 0.0, 0.0,
+        ]);
+        let mut pool = MaxPool2D::new(3, 3, (2, 2), (1, 1));
+        let output = pool.feed_forward(&input).unwrap();
+        assert_eq!((output.rows, output.cols), (2, 4));
+        assert_eq!(output.data, vec![5.0, 6.0, 8.0, 9.0, 9.0, 9.0, 9.0, 9.0]);
+
+        // A value that wins several overlapping windows accumulates their gradients
+        let error = Matrix { rows: 2, cols: 4, data: vec![1.0; 8] };
+        let input_error = pool.backpropagate(&error, 0.1).unwrap();
+        assert_eq!(input_error.get(0, 4), 1.0);
+        assert_eq!(input_error.get(0, 8), 1.0);
+        assert_eq!(input_error.get(1, 4), 4.0);
+        assert_eq!(input_error.get(1, 0), 0.0);
+    }
+
+    #[test]
+    fn test_avg_pool() {
+        let input = feature_map(1, &[
+            1.0, 3.0,
+            5.0, 7.0,
+        ]);
+        let mut pool = AvgPool2D::new(2, 2, (2, 2), (2, 2));
+        let output = pool.feed_forward(&input).unwrap();
+        assert_eq!(output.data, vec![4.0]);
+
+        let input_error = pool.backpropagate(&Matrix::from_array(&[2.0]), 0.1).unwrap();
+        assert_eq!(input_error.data, vec![0.5; 4]);
+    }
+
+    #[test]
+    fn test_global_average_pool_and_flatten() {
+        let input = feature_map(2, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let mut gap = GlobalAveragePool::new();
+        let output = gap.feed_forward(&input).unwrap();
+        assert_eq!(output.data, vec![2.0, 5.0]);
+        let input_error = gap.backpropagate(&Matrix::from_array(&[3.0, 6.0]), 0.1).unwrap();
+        assert_eq!(input_error.data, vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+
+        let mut flatten = Flatten::new();
+        let flat = flatten.feed_forward(&input).unwrap();
+        assert_eq!((flat.rows, flat.cols), (6, 1));
+        let restored = flatten.backpropagate(&flat, 0.1).unwrap();
+        assert_eq!((restored.rows, restored.cols), (2, 3));
+        assert_eq!(restored.data, input.data);
+    }
+
+    #[test]
+    fn test_pool_rejects_bad_dimensions() {
+        let mut pool = MaxPool2D::new(2, 2, (3, 3), (1, 1));
+        assert!(pool.feed_forward(&feature_map(1, &[0.0; 4])).is_err());
+
+        let mut pool = AvgPool2D::new(3, 3, (2, 2), (1, 1));
+        assert!(pool.feed_forward(&feature_map(1, &[0.0; 4])).is_err());
+    }
+}
@@ -0,0 +1,662 @@
+use crate::activation::{ActivationFunction, Sigmoid, Tanh};
+use crate::clipping::GradientClipping;
+use crate::matrix::Matrix;
+use std::sync::Arc;
+
+// Sequences are laid out as `features x time`, one column per time step.
+
+fn matvec(m: &Matrix, row_start: usize, rows: usize, v: &[f64]) -> Vec<f64> {
+    (row_start..row_start + rows)
+        .map(|i| {
+            let row = &m.data[i * m.cols..(i + 1) * m.cols];
+            row.iter().zip(v).map(|(a, b)| a * b).sum()
+        })
+        .collect()
+}
+
+fn matvec_transpose(m: &Matrix, row_start: usize, v: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; m.cols];
+    for (k, &scale) in v.iter().enumerate() {
+        let row = &m.data[(row_start + k) * m.cols..(row_start + k + 1) * m.cols];
+        for (r, &w) in result.iter_mut().zip(row) {
+            *r += w * scale;
+        }
+    }
+    result
+}
+
+fn add_outer(g: &mut Matrix, row_start: usize, a: &[f64], b: &[f64]) {
+    for (k, &scale) in a.iter().enumerate() {
+        let row = &mut g.data[(row_start + k) * g.cols..(row_start + k + 1) * g.cols];
+        for (r, &v) in row.iter_mut().zip(b) {
+            *r += scale * v;
+        }
+    }
+}
+
+fn add_into(target: &mut [f64], source: &[f64]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t += s;
+    }
+}
+
+fn random_matrix(rows: usize, cols: usize) -> Matrix {
+    let mut m = Matrix::new(rows, cols);
+    m.randomize();
+    m
+}
+
+// One step of a recurrent layer. `state` holds everything carried between steps,
+// with the hidden output always in its first `hidden_size` entries.
+pub trait RecurrentCell: Send + Sync {
+    type Cache: Send + Sync;
+
+    fn input_size(&self) -> usize;
+    fn hidden_size(&self) -> usize;
+    fn state_size(&self) -> usize;
+
+    fn step(&self, x: &[f64], state: &[f64]) -> (Vec<f64>, Self::Cache);
+
+    // Accumulates parameter gradients and returns (input error, previous state error).
+    fn step_back(&mut self, cache: &Self::Cache, state_error: &[f64]) -> (Vec<f64>, Vec<f64>);
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix>;
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix>;
+
+    fn apply_gradients(&mut self, learning_rate: f64);
+}
+
+pub struct RecurrentWeights {
+    pub input_weights: Matrix,
+    pub hidden_weights: Matrix,
+    pub biases: Matrix,
+    input_gradient: Matrix,
+    hidden_gradient: Matrix,
+    bias_gradient: Matrix,
+}
+
+impl RecurrentWeights {
+    fn new(gates: usize, input_size: usize, hidden_size: usize) -> Self {
+        RecurrentWeights {
+            input_weights: random_matrix(gates * hidden_size, input_size),
+            hidden_weights: random_matrix(gates * hidden_size, hidden_size),
+            biases: random_matrix(gates * hidden_size, 1),
+            input_gradient: Matrix::new(gates * hidden_size, input_size),
+            hidden_gradient: Matrix::new(gates * hidden_size, hidden_size),
+            bias_gradient: Matrix::new(gates * hidden_size, 1),
+        }
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.input_weights, &mut self.hidden_weights, &mut self.biases]
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix> {
+        vec![&mut self.input_gradient, &mut self.hidden_gradient, &mut self.bias_gradient]
+    }
+
+    fn apply_gradients(&mut self, learning_rate: f64) {
+        let pairs = [
+            (&mut self.input_weights, &mut self.input_gradient),
+            (&mut self.hidden_weights, &mut self.hidden_gradient),
+            (&mut self.biases, &mut self.bias_gradient),
+        ];
+        for (weights, gradient) in pairs {
+            for (w, g) in weights.data.iter_mut().zip(gradient.data.iter_mut()) {
+                *w += learning_rate * *g;
+                *g = 0.0;
+            }
+        }
+    }
+}
+
+pub struct RnnCell {
+    pub weights: RecurrentWeights,
+    activation: Arc<dyn ActivationFunction>,
+}
+
+pub struct RnnCache {
+    x: Vec<f64>,
+    h_prev: Vec<f64>,
+    h: Vec<f64>,
+}
+
+impl RecurrentCell for RnnCell {
+    type Cache = RnnCache;
+
+    fn input_size(&self) -> usize {
+        self.weights.input_weights.cols
+    }
+
+    fn hidden_size(&self) -> usize {
+        self.weights.hidden_weights.cols
+    }
+
+    fn state_size(&self) -> usize {
+        self.hidden_size()
+    }
+
+    fn step(&self, x: &[f64], state: &[f64]) -> (Vec<f64>, RnnCache) {
+        let w = &self.weights;
+        let hidden = self.hidden_size();
+        let mut z = matvec(&w.input_weights, 0, hidden, x);
+        add_into(&mut z, &matvec(&w.hidden_weights, 0, hidden, state));
+        add_into(&mut z, &w.biases.data);
+        let h: Vec<f64> = z.iter().map(|&v| self.activation.activate(v)).collect();
+
+        let cache = RnnCache { x: x.to_vec(), h_prev: state.to_vec(), h: h.clone() };
+        (h, cache)
+    }
+
+    fn step_back(&mut self, cache: &RnnCache, state_error: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let delta: Vec<f64> = state_error.iter().zip(&cache.h)
+            .map(|(e, &h)| e * self.activation.derivative(h))
+            .collect();
+
+        let w = &mut self.weights;
+        add_outer(&mut w.input_gradient, 0, &delta, &cache.x);
+        add_outer(&mut w.hidden_gradient, 0, &delta, &cache.h_prev);
+        add_into(&mut w.bias_gradient.data, &delta);
+
+        (matvec_transpose(&w.input_weights, 0, &delta), matvec_transpose(&w.hidden_weights, 0, &delta))
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.gradients_mut()
+    }
+
+    fn apply_gradients(&mut self, learning_rate: f64) {
+        self.weights.apply_gradients(learning_rate);
+    }
+}
+
+// Gate blocks are stacked as [input, forget, output, candidate]; the state is [h; c].
+pub struct LstmCell {
+    pub weights: RecurrentWeights,
+}
+
+pub struct LstmCache {
+    x: Vec<f64>,
+    h_prev: Vec<f64>,
+    c_prev: Vec<f64>,
+    gates: Vec<f64>,
+    c_tanh: Vec<f64>,
+}
+
+impl RecurrentCell for LstmCell {
+    type Cache = LstmCache;
+
+    fn input_size(&self) -> usize {
+        self.weights.input_weights.cols
+    }
+
+    fn hidden_size(&self) -> usize {
+        self.weights.hidden_weights.cols
+    }
+
+    fn state_size(&self) -> usize {
+        2 * self.hidden_size()
+    }
+
+    fn step(&self, x: &[f64], state: &[f64]) -> (Vec<f64>, LstmCache) {
+        let w = &self.weights;
+        let hidden = self.hidden_size();
+        let (h_prev, c_prev) = state.split_at(hidden);
+
+        let mut gates = matvec(&w.input_weights, 0, 4 * hidden, x);
+        add_into(&mut gates, &matvec(&w.hidden_weights, 0, 4 * hidden, h_prev));
+        add_into(&mut gates, &w.biases.data);
+        for (k, g) in gates.iter_mut().enumerate() {
+            *g = if k < 3 * hidden { Sigmoid.activate(*g) } else { Tanh.activate(*g) };
+        }
+
+        let mut next = vec![0.0; 2 * hidden];
+        let mut c_tanh = vec![0.0; hidden];
+        for j in 0..hidden {
+            let (i, f, o, g) = (gates[j], gates[hidden + j], gates[2 * hidden + j], gates[3 * hidden + j]);
+            let c = f * c_prev[j] + i * g;
+            c_tanh[j] = c.tanh();
+            next[j] = o * c_tanh[j];
+            next[hidden + j] = c;
+        }
+
+        let cache = LstmCache {
+            x: x.to_vec(),
+            h_prev: h_prev.to_vec(),
+            c_prev: c_prev.to_vec(),
+            gates,
+            c_tanh,
+        };
+        (next, cache)
+    }
+
+    fn step_back(&mut self, cache: &LstmCache, state_error: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let hidden = self.hidden_size();
+        let (dh, dc_next) = state_error.split_at(hidden);
+        let gates = &cache.gates;
+
+        let mut gate_delta = vec![0.0; 4 * hidden];
+        let mut prev_error = vec![0.0; 2 * hidden];
+        for j in 0..hidden {
+            let (i, f, o, g) = (gates[j], gates[hidden + j], gates[2 * hidden + j], gates[3 * hidden + j]);
+            let dc = dc_next[j] + dh[j] * o * Tanh.derivative(cache.c_tanh[j]);
+
+            gate_delta[j] = dc * g * Sigmoid.derivative(i);
+            gate_delta[hidden + j] = dc * cache.c_prev[j] * Sigmoid.derivative(f);
+            gate_delta[2 * hidden + j] = dh[j] * cache.c_tanh[j] * Sigmoid.derivative(o);
+            gate_delta[3 * hidden + j] = dc * i * Tanh.derivative(g);
+            prev_error[hidden + j] = dc * f;
+        }
+
+        let w = &mut self.weights;
+        add_outer(&mut w.input_gradient, 0, &gate_delta, &cache.x);
+        add_outer(&mut w.hidden_gradient, 0, &gate_delta, &cache.h_prev);
+        add_into(&mut w.bias_gradient.data, &gate_delta);
+
+        let dh_prev = matvec_transpose(&w.hidden_weights, 0, &gate_delta);
+        prev_error[..hidden].copy_from_slice(&dh_prev);
+
+        (matvec_transpose(&w.input_weights, 0, &gate_delta), prev_error)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.gradients_mut()
+    }
+
+    fn apply_gradients(&mut self, learning_rate: f64) {
+        self.weights.apply_gradients(learning_rate);
+    }
+}
+
+// Gate blocks are stacked as [update, reset, candidate]. The candidate sees the
+// reset-gated previous state: n = tanh(W_n x + U_n (r * h) + b_n).
+pub struct GruCell {
+    pub weights: RecurrentWeights,
+}
+
+pub struct GruCache {
+    x: Vec<f64>,
+    h_prev: Vec<f64>,
+    reset_hidden: Vec<f64>,
+    z: Vec<f64>,
+    r: Vec<f64>,
+    n: Vec<f64>,
+}
+
+impl RecurrentCell for GruCell {
+    type Cache = GruCache;
+
+    fn input_size(&self) -> usize {
+        self.weights.input_weights.cols
+    }
+
+    fn hidden_size(&self) -> usize {
+        self.weights.hidden_weights.cols
+    }
+
+    fn state_size(&self) -> usize {
+        self.hidden_size()
+    }
+
+    fn step(&self, x: &[f64], state: &[f64]) -> (Vec<f64>, GruCache) {
+        let w = &self.weights;
+        let hidden = self.hidden_size();
+
+        let mut pre = matvec(&w.input_weights, 0, 3 * hidden, x);
+        add_into(&mut pre, &w.biases.data);
+        add_into(&mut pre[..2 * hidden], &matvec(&w.hidden_weights, 0, 2 * hidden, state));
+
+        let z: Vec<f64> = pre[..hidden].iter().map(|&v| Sigmoid.activate(v)).collect();
+        let r: Vec<f64> = pre[hidden..2 * hidden].iter().map(|&v| Sigmoid.activate(v)).collect();
+        let reset_hidden: Vec<f64> = r.iter().zip(state).map(|(r, h)| r * h).collect();
+
+        let mut n = pre[2 * hidden..].to_vec();
+        add_into(&mut n, &matvec(&w.hidden_weights, 2 * hidden, hidden, &reset_hidden));
+        n.iter_mut().for_each(|v| *v = Tanh.activate(*v));
+
+        let h: Vec<f64> = (0..hidden).map(|j| (1.0 - z[j]) * n[j] + z[j] * state[j]).collect();
+
+        let cache = GruCache { x: x.to_vec(), h_prev: state.to_vec(), reset_hidden, z, r, n };
+        (h, cache)
+    }
+
+    fn step_back(&mut self, cache: &GruCache, state_error: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let hidden = self.hidden_size();
+        let w = &mut self.weights;
+
+        let mut gate_delta = vec![0.0; 3 * hidden];
+        let mut dh_prev = vec![0.0; hidden];
+        for j in 0..hidden {
+            let dh = state_error[j];
+            gate_delta[j] = dh * (cache.h_prev[j] - cache.n[j]) * Sigmoid.derivative(cache.z[j]);
+            gate_delta[2 * hidden + j] = dh * (1.0 - cache.z[j]) * Tanh.derivative(cache.n[j]);
+            dh_prev[j] = dh * cache.z[j];
+        }
+
+        let candidate_delta = &gate_delta[2 * hidden..];
+        let d_reset_hidden = matvec_transpose(&w.hidden_weights, 2 * hidden, candidate_delta);
+        for j in 0..hidden {
+            gate_delta[hidden + j] = d_reset_hidden[j] * cache.h_prev[j] * Sigmoid.derivative(cache.r[j]);
+            dh_prev[j] += d_reset_hidden[j] * cache.r[j];
+        }
+
+        add_outer(&mut w.input_gradient, 0, &gate_delta, &cache.x);
+        add_into(&mut w.bias_gradient.data, &gate_delta);
+        add_outer(&mut w.hidden_gradient, 0, &gate_delta[..2 * hidden], &cache.h_prev);
+        add_outer(&mut w.hidden_gradient, 2 * hidden, &gate_delta[2 * hidden..], &cache.reset_hidden);
+        add_into(&mut dh_prev, &matvec_transpose(&w.hidden_weights, 0, &gate_delta[..2 * hidden]));
+
+        (matvec_transpose(&w.input_weights, 0, &gate_delta), dh_prev)
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.parameters_mut()
+    }
+
+    fn gradients_mut(&mut self) -> Vec<&mut Matrix> {
+        self.weights.gradients_mut()
+    }
+
+    fn apply_gradients(&mut self, learning_rate: f64) {
+        self.weights.apply_gradients(learning_rate);
+    }
+}
+
+pub struct Recurrent<C: RecurrentCell> {
+    pub cell: C,
+    pub return_sequences: bool,
+    pub stateful: bool,
+    // When set, gradients only flow back through windows of this many steps,
+    // as if the hidden state were detached between windows.
+    pub truncate: Option<usize>,
+    // Applied to the gradients accumulated over the whole sequence before the update.
+    pub gradient_clipping: Option<GradientClipping>,
+    state: Option<Vec<f64>>,
+    caches: Vec<C::Cache>,
+}
+
+pub type SimpleRNN = Recurrent<RnnCell>;
+pub type LSTM = Recurrent<LstmCell>;
+pub type GRU = Recurrent<GruCell>;
+
+impl SimpleRNN {
+    pub fn new(input_size: usize, hidden_size: usize, activation: Arc<dyn ActivationFunction>) -> Self {
+        Recurrent::with_cell(RnnCell {
+            weights: RecurrentWeights::new(1, input_size, hidden_size),
+            activation,
+        })
+    }
+}
+
+impl LSTM {
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Recurrent::with_cell(LstmCell {
+            weights: RecurrentWeights::new(4, input_size, hidden_size),
+        })
+    }
+}
+
+impl GRU {
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Recurrent::with_cell(GruCell {
+            weights: RecurrentWeights::new(3, input_size, hidden_size),
+        })
+    }
+}
+
+impl<C: RecurrentCell> Recurrent<C> {
+    pub fn with_cell(cell: C) -> Self {
+        Recurrent {
+            cell,
+            return_sequences: false,
+            stateful: false,
+            truncate: None,
+            gradient_clipping: None,
+            state: None,
+            caches: Vec::new(),
+        }
+    }
+
+    pub fn return_sequences(mut self, return_sequences: bool) -> Self {
+        self.return_sequences = return_sequences;
+        self
+    }
+
+    pub fn stateful(mut self, stateful: bool) -> Self {
+        self.stateful = stateful;
+        self
+    }
+
+    pub fn truncate(mut self, steps: usize) -> Self {
+        self.truncate = Some(steps);
+        self
+    }
+
+    pub fn gradient_clipping(mut self, clipping: GradientClipping) -> Result<Self, &'static str> {
+        clipping.validate()?;
+        self.gradient_clipping = Some(clipping);
+        Ok(self)
+    }
+
+    pub fn reset_state(&mut self) {
+        self.state = None;
+    }
+
+    pub fn hidden_state(&self) -> Option<&[f64]> {
+        self.state.as_ref().map(|s| &s[..self.cell.hidden_size()])
+    }
+
+    pub fn feed_forward(&mut self, input: &Matrix) -> Result<Matrix, &'static str> {
+        if input.rows != self.cell.input_size() {
+            return Err("Input size does not match recurrent layer");
+        }
+        if input.cols == 0 {
+            return Err("Cannot run a recurrent layer on an empty sequence");
+        }
+
+        let hidden = self.cell.hidden_size();
+        let steps = input.cols;
+        let transposed = Matrix::transpose(input);
+
+        let mut state = match (&self.state, self.stateful) {
+            (Some(state), true) => state.clone(),
+            _ => vec![0.0; self.cell.state_size()],
+        };
+
+        let mut outputs = Matrix::new(hidden, steps);
+        self.caches.clear();
+        for t in 0..steps {
+            let x = &transposed.data[t * input.rows..(t + 1) * input.rows];
+            let (next, cache) = self.cell.step(x, &state);
+            for (j, &h) in next.iter().enumerate().take(hidden) {
+                outputs.data[j * steps + t] = h;
+            }
+            self.caches.push(cache);
+            state = next;
+        }
+
+        let output = if self.return_sequences {
+            outputs
+        } else {
+            Matrix::from_array(&state[..hidden])
+        };
+        self.state = Some(state);
+
+        Ok(output)
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<&mut Matrix> {
+        self.cell.parameters_mut()
+    }
+
+    pub fn backpropagate(&mut self, output_error: &Matrix, learning_rate: f64) -> Result<Matrix, &'static str> {
+        if self.caches.is_empty() {
+            return Err("No input stored for backpropagation");
+        }
+
+        let hidden = self.cell.hidden_size();
+        let steps = self.caches.len();
+        let expected_cols = if self.return_sequences { steps } else { 1 };
+        if output_error.rows != hidden || output_error.cols != expected_cols {
+            return Err("Matrix size mismatch");
+        }
+
+        let input_size = self.cell.input_size();
+        let mut input_error = Matrix::new(input_size, steps);
+        let mut carried = vec![0.0; self.cell.state_size()];
+        let caches = std::mem::take(&mut self.caches);
+
+        for t in (0..steps).rev() {
+            if let Some(window) = self.truncate {
+                if window > 0 && (steps - 1 - t).is_multiple_of(window) && t != steps - 1 {
+                    carried.iter_mut().for_each(|v| *v = 0.0);
+                }
+            }
+
+            let error_col = if self.return_sequences {
+                Some(t)
+            } else if t == steps - 1 {
+                Some(0)
+            } else {
+                None
+            };
+            if let Some(col) = error_col {
+                for (j, c) in carried.iter_mut().enumerate().take(hidden) {
+                    *c += output_error.data[j * output_error.cols + col];
+                }
+            }
+
+            let (dx, d_prev) = self.cell.step_back(&caches[t], &carried);
+            for (i, v) in dx.iter().enumerate() {
+                input_error.data[i * steps + t] = *v;
+            }
+            carried = d_prev;
+        }
+
+        self.caches = caches;
+        if let Some(clipping) = self.gradient_clipping {
+            clipping.apply(&mut [self.cell.gradients_mut()]);
+        }
+        self.cell.apply_gradients(learning_rate);
+
+        Ok(input_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck;
+
+    fn sequence() -> Matrix {
+        Matrix {
+            rows: 2,
+            cols: 4,
+            data: vec![0.5, -0.3, 0.8, 0.1, -0.6, 0.2, 0.4, -0.9],
+        }
+    }
+
+    fn check_gradients<C: RecurrentCell>(layer: &mut Recurrent<C>, weights: fn(&mut Recurrent<C>) -> &mut Matrix) {
+        let input = sequence();
+        let report = gradcheck::check_module(layer, &input).unwrap();
+        assert!(report.passed(1e-6), "input gradient {:?}", report);
+        let report = gradcheck::check_module_parameters(layer, &input, weights).unwrap();
+        assert!(report.passed(1e-5), "weight gradient {:?}", report);
+    }
+
+    #[test]
+    fn test_simple_rnn_gradients() {
+        let mut rnn = SimpleRNN::new(2, 3, Arc::new(Tanh)).return_sequences(true);
+        check_gradients(&mut rnn, |l| &mut l.cell.weights.hidden_weights);
+        let mut rnn = SimpleRNN::new(2, 3, Arc::new(Tanh));
+        check_gradients(&mut rnn, |l| &mut l.cell.weights.input_weights);
+    }
+
+    #[test]
+    fn test_lstm_gradients() {
+        let mut lstm = LSTM::new(2, 3).return_sequences(true);
+        check_gradients(&mut lstm, |l| &mut l.cell.weights.hidden_weights);
+        let mut lstm = LSTM::new(2, 3);
+        check_gradients(&mut lstm, |l| &mut l.cell.weights.biases);
+    }
+
+    #[test]
+    fn test_gru_gradients() {
+        let mut gru = GRU::new(2, 3).return_sequences(true);
+        check_gradients(&mut gru, |l| &mut l.cell.weights.hidden_weights);
+        let mut gru = GRU::new(2, 3);
+        check_gradients(&mut gru, |l| &mut l.cell.weights.input_weights);
+    }
+
+    #[test]
+    fn test_output_modes() {
+        let mut lstm = LSTM::new(2, 5);
+        let last = lstm.feed_forward(&sequence()).unwrap();
+        assert_eq!((last.rows, last.cols), (5, 1));
+
+        let mut lstm = lstm.return_sequences(true);
+        let all = lstm.feed_forward(&sequence()).unwrap();
+        assert_eq!((all.rows, all.cols), (5, 4));
+        for j in 0..5 {
+            assert!((all.get(j, 3) - last.get(j, 0)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_stateful_carries_hidden_state() {
+        let input = sequence();
+        let mut full = GRU::new(2, 3);
+        let expected = full.feed_forward(&input).unwrap();
+
+        let mut split = GRU::new(2, 3).stateful(true);
+        split.cell.weights.input_weights = full.cell.weights.input_weights.clone();
+        split.cell.weights.hidden_weights = full.cell.weights.hidden_weights.clone();
+        split.cell.weights.biases = full.cell.weights.biases.clone();
+
+        let first = Matrix { rows: 2, cols: 2, data: vec![0.5, -0.3, -0.6, 0.2] };
+        let second = Matrix { rows: 2, cols: 2, data: vec![0.8, 0.1, 0.4, -0.9] };
+        split.feed_forward(&first).unwrap();
+        let output = split.feed_forward(&second).unwrap();
+        for (a, b) in output.data.iter().zip(&expected.data) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        split.reset_state();
+        assert!(split.hidden_state().is_none());
+    }
+
+    #[test]
+    fn test_truncated_backpropagation() {
+        let mut rnn = SimpleRNN::new(2, 3, Arc::new(Tanh)).truncate(2);
+        rnn.feed_forward(&sequence()).unwrap();
+        let input_error = rnn.backpropagate(&Matrix::from_array(&[1.0, 1.0, 1.0]), 0.0).unwrap();
+
+        // Only the last two steps receive gradient from the final state
+        for i in 0..2 {
+            assert_eq!(input_error.get(i, 0), 0.0);
+            assert_eq!(input_error.get(i, 1), 0.0);
+            assert!(input_error.get(i, 3).abs() > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_gradient_clipping_limits_update() {
+        let mut rnn = SimpleRNN::new(2, 3, Arc::new(Tanh)).gradient_clipping(GradientClipping::Value(0.01)).unwrap();
+        let before = rnn.cell.weights.hidden_weights.clone();
+        rnn.feed_forward(&sequence()).unwrap();
+        rnn.backpropagate(&Matrix::from_array(&[100.0, -100.0, 100.0]), 1.0).unwrap();
+
+        for (after, before) in rnn.cell.weights.hidden_weights.data.iter().zip(&before.data) {
+            assert!((after - before).abs() <= 0.01 + 1e-12);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::float::Float;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Regularizer {
+    L1(f64),
+    L2(f64),
+    ElasticNet { l1: f64, l2: f64 },
+}
+
+impl Regularizer {
+    pub fn penalty<T: Float>(&self, weights: &[T]) -> f64 {
+        let (l1, l2) = self.coefficients();
+        weights.iter().map(|w| w.to_f64()).map(|w| l1 * w.abs() + l2 * w * w).sum()
+    }
+
+    // Derivative of the penalty with respect to a single weight.
+    pub fn gradient(&self, weight: f64) -> f64 {
+        let (l1, l2) = self.coefficients();
+        let sign = if weight > 0.0 {
+            1.0
+        } else if weight < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
+        l1 * sign + 2.0 * l2 * weight
+    }
+
+    fn coefficients(&self) -> (f64, f64) {
+        match *self {
+            Regularizer::L1(l1) => (l1, 0.0),
+            Regularizer::L2(l2) => (0.0, l2),
+            Regularizer::ElasticNet { l1, l2 } => (l1, l2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalties() {
+        let weights = [1.0, -2.0, 0.5];
+        assert!((Regularizer::L1(0.1).penalty(&weights) - 0.35).abs() < 1e-12);
+        assert!((Regularizer::L2(0.1).penalty(&weights) - 0.525).abs() < 1e-12);
+        let elastic = Regularizer::ElasticNet { l1: 0.1, l2: 0.1 };
+        assert!((elastic.penalty(&weights) - 0.875).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_gradients() {
+        assert_eq!(Regularizer::L1(0.1).gradient(-3.0), -0.1);
+        assert_eq!(Regularizer::L1(0.1).gradient(0.0), 0.0);
+        assert!((Regularizer::L2(0.1).gradient(-3.0) + 0.6).abs() < 1e-12);
+        let elastic = Regularizer::ElasticNet { l1: 0.1, l2: 0.5 };
+        assert!((elastic.gradient(2.0) - 2.1).abs() < 1e-12);
+    }
+}
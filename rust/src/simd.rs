@@ -0,0 +1,773 @@
+// Hand-vectorized element-wise kernels with runtime CPU feature detection.
+//
+// Each kernel is written once against `Lanes`, a thin wrapper over one SIMD
+// register, and instantiated per instruction set inside a `#[target_feature]`
+// entry point so the intrinsics inline. The widest set the CPU supports is
+// picked on first use; anything else runs the scalar fallback.
+//
+// exp, sigmoid and tanh are polynomial approximations on the SIMD paths:
+// exp is within a few ulp (relative 1e-14 for f64, 1e-6 for f32), and sigmoid
+// and tanh inherit that as absolute error. The scalar fallback uses std.
+use crate::float::Float;
+use std::sync::OnceLock;
+
+// Widest register in elements (AVX-512 with f32); sizes the tail buffer.
+const MAX_WIDTH: usize = 16;
+
+// 1 / k! for the exp polynomial.
+const INV_FACTORIALS: [f64; 13] = [
+    1.0,
+    1.0,
+    1.0 / 2.0,
+    1.0 / 6.0,
+    1.0 / 24.0,
+    1.0 / 120.0,
+    1.0 / 720.0,
+    1.0 / 5040.0,
+    1.0 / 40320.0,
+    1.0 / 362880.0,
+    1.0 / 3628800.0,
+    1.0 / 39916800.0,
+    1.0 / 479001600.0,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+// Whether the CPU has every instruction set `level`'s kernels are compiled for.
+pub fn supported(level: Level) -> bool {
+    match level {
+        Level::Scalar => true,
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma"),
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => is_x86_feature_detected!("avx512f"),
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+    }
+}
+
+// Every level built for this architecture, widest first.
+#[cfg(target_arch = "x86_64")]
+const COMPILED_LEVELS: &[Level] = &[Level::Avx512, Level::Avx2, Level::Scalar];
+#[cfg(target_arch = "aarch64")]
+const COMPILED_LEVELS: &[Level] = &[Level::Neon, Level::Scalar];
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const COMPILED_LEVELS: &[Level] = &[Level::Scalar];
+
+// Elements of `T` per register at `level`.
+fn width<T: Float>(level: Level) -> usize {
+    match level {
+        Level::Scalar => 1,
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => <T::Avx2 as Lanes>::WIDTH,
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => <T::Avx512 as Lanes>::WIDTH,
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => <T::Neon as Lanes>::WIDTH,
+    }
+}
+
+// The widest supported level.
+pub fn detected() -> Level {
+    static LEVEL: OnceLock<Level> = OnceLock::new();
+    *LEVEL.get_or_init(|| {
+        COMPILED_LEVELS.iter().copied().find(|&level| supported(level)).unwrap_or(Level::Scalar)
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Binary {
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Unary<T> {
+    Scale(T),
+    Exp,
+    Sigmoid,
+    Tanh,
+}
+
+// `x[i] = x[i] op y[i]` over the common length.
+pub fn binary<T: Float>(op: Binary, x: &mut [T], y: &[T]) {
+    // Safety: the detected level is supported
+    unsafe { binary_at(detected(), op, x, y) }
+}
+
+pub fn unary<T: Float>(op: Unary<T>, x: &mut [T]) {
+    // Safety: the detected level is supported
+    unsafe { unary_at(detected(), op, x) }
+}
+
+// The `_at` functions run one level's kernels without checking the CPU.
+// Safety: `level` must be `supported`.
+unsafe fn binary_at<T: Float>(level: Level, op: Binary, x: &mut [T], y: &[T]) {
+    match level {
+        Level::Scalar => scalar_binary(op, x, y),
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { avx2::binary(op, x, y) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { avx512::binary(op, x, y) },
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => unsafe { neon::binary(op, x, y) },
+    }
+}
+
+unsafe fn unary_at<T: Float>(level: Level, op: Unary<T>, x: &mut [T]) {
+    match level {
+        Level::Scalar => scalar_unary(op, x),
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { avx2::unary(op, x) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { avx512::unary(op, x) },
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => unsafe { neon::unary(op, x) },
+    }
+}
+
+// The `gemm` micro-kernel: adds the product of an MR-tall panel of A, stored
+// column by column, and an NR-wide panel of B, stored row by row, to `acc`.
+pub(crate) fn outer_products<T: Float, const MR: usize, const NR: usize>(a: &[T], b: &[T], acc: &mut [[T; NR]; MR]) {
+    // Safety: the detected level is supported
+    unsafe { outer_products_at(detected(), a, b, acc) }
+}
+
+// The level whose kernel `outer_products_at(level, ..)` runs: the widest
+// supported one at or below `level` whose registers evenly split a row of NR.
+// That sends f32 on AVX-512, 16 lanes against NR = 8, to the AVX2 kernel.
+fn outer_products_level<T: Float, const NR: usize>(level: Level) -> Level {
+    COMPILED_LEVELS.iter().copied()
+        .skip_while(|&l| l != level)
+        .find(|&l| NR.is_multiple_of(width::<T>(l)) && supported(l))
+        .unwrap_or(Level::Scalar)
+}
+
+unsafe fn outer_products_at<T: Float, const MR: usize, const NR: usize>(level: Level, a: &[T], b: &[T], acc: &mut [[T; NR]; MR]) {
+    match outer_products_level::<T, NR>(level) {
+        Level::Scalar => scalar_outer_products(a, b, acc),
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { avx2::outer_products(a, b, acc) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx512 => unsafe { avx512::outer_products(a, b, acc) },
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => unsafe { neon::outer_products(a, b, acc) },
+    }
+}
+
+// Fixed-size arrays let the compiler keep `acc` in registers and unroll.
+#[inline(always)]
+fn scalar_outer_products<T: Float, const MR: usize, const NR: usize>(a: &[T], b: &[T], acc: &mut [[T; NR]; MR]) {
+    for (a_col, b_row) in a.chunks_exact(MR).zip(b.chunks_exact(NR)) {
+        for (acc_row, &a_i) in acc.iter_mut().zip(a_col) {
+            for (value, &b_j) in acc_row.iter_mut().zip(b_row) {
+                *value += a_i * b_j;
+            }
+        }
+    }
+}
+
+fn scalar_binary<T: Float>(op: Binary, x: &mut [T], y: &[T]) {
+    let pairs = x.iter_mut().zip(y);
+    match op {
+        Binary::Add => pairs.for_each(|(a, &b)| *a += b),
+        Binary::Sub => pairs.for_each(|(a, &b)| *a -= b),
+        Binary::Mul => pairs.for_each(|(a, &b)| *a *= b),
+    }
+}
+
+fn scalar_unary<T: Float>(op: Unary<T>, x: &mut [T]) {
+    match op {
+        Unary::Scale(s) => x.iter_mut().for_each(|a| *a *= s),
+        Unary::Exp => x.iter_mut().for_each(|a| *a = a.exp()),
+        Unary::Sigmoid => x.iter_mut().for_each(|a| *a = T::ONE / (T::ONE + (-*a).exp())),
+        Unary::Tanh => x.iter_mut().for_each(|a| *a = a.tanh()),
+    }
+}
+
+// Per-element-type registers and exp constants; a supertrait of `Float`.
+pub trait Simd: Sized {
+    #[cfg(target_arch = "x86_64")]
+    type Avx2: Lanes<Elem = Self>;
+    #[cfg(target_arch = "x86_64")]
+    type Avx512: Lanes<Elem = Self>;
+    #[cfg(target_arch = "aarch64")]
+    type Neon: Lanes<Elem = Self>;
+
+    // Adding this to an integral float leaves `n + bias` in the low mantissa bits.
+    const EXPONENT_MAGIC: f64;
+    // exp saturates to 0 / infinity outside this range.
+    const EXP_MIN: f64;
+    const EXP_MAX: f64;
+    // ln 2 split so that n * LN2_HI is exact.
+    const LN2_HI: f64;
+    const LN2_LO: f64;
+    const EXP_DEGREE: usize;
+}
+
+impl Simd for f64 {
+    #[cfg(target_arch = "x86_64")]
+    type Avx2 = x86::Avx2F64;
+    #[cfg(target_arch = "x86_64")]
+    type Avx512 = x86::Avx512F64;
+    #[cfg(target_arch = "aarch64")]
+    type Neon = arm::NeonF64;
+
+    const EXPONENT_MAGIC: f64 = 4503599627370496.0 + 1023.0;
+    const EXP_MIN: f64 = -708.0;
+    const EXP_MAX: f64 = 709.0;
+    const LN2_HI: f64 = 6.931_471_803_691_238e-1;
+    const LN2_LO: f64 = 1.908_214_929_270_587_7e-10;
+    const EXP_DEGREE: usize = 12;
+}
+
+impl Simd for f32 {
+    #[cfg(target_arch = "x86_64")]
+    type Avx2 = x86::Avx2F32;
+    #[cfg(target_arch = "x86_64")]
+    type Avx512 = x86::Avx512F32;
+    #[cfg(target_arch = "aarch64")]
+    type Neon = arm::NeonF32;
+
+    const EXPONENT_MAGIC: f64 = 8388608.0 + 127.0;
+    const EXP_MIN: f64 = -87.0;
+    const EXP_MAX: f64 = 88.0;
+    const LN2_HI: f64 = 0.693_359_375;
+    const LN2_LO: f64 = -2.121_944_4e-4;
+    const EXP_DEGREE: usize = 7;
+}
+
+// One SIMD register of `WIDTH` elements. All methods need the instruction set
+// the implementation is written for.
+#[allow(clippy::missing_safety_doc)]
+pub trait Lanes: Copy {
+    type Elem: Float;
+    const WIDTH: usize;
+
+    unsafe fn splat(value: Self::Elem) -> Self;
+    unsafe fn load(ptr: *const Self::Elem) -> Self;
+    unsafe fn store(self, ptr: *mut Self::Elem);
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn sub(self, other: Self) -> Self;
+    unsafe fn mul(self, other: Self) -> Self;
+    unsafe fn div(self, other: Self) -> Self;
+    // self * b + c
+    unsafe fn mul_add(self, b: Self, c: Self) -> Self;
+    // Both return `other` when either is NaN, so `lo.max(x)` keeps a NaN `x`.
+    unsafe fn max(self, other: Self) -> Self;
+    unsafe fn min(self, other: Self) -> Self;
+    unsafe fn round(self) -> Self;
+    // 2^n, given `n + EXPONENT_MAGIC` for an integral n in the normal exponent range.
+    unsafe fn pow2(self) -> Self;
+    // `if_true` where self > threshold, otherwise `if_false`.
+    unsafe fn select_gt(self, threshold: Self, if_true: Self, if_false: Self) -> Self;
+}
+
+mod kernel {
+    use super::*;
+
+    #[inline(always)]
+    unsafe fn splat<V: Lanes>(value: f64) -> V {
+        V::splat(V::Elem::from_f64(value))
+    }
+
+    // Runs `f` over full registers, then over the tail padded out to a register.
+    #[inline(always)]
+    unsafe fn zip<V: Lanes>(x: &mut [V::Elem], y: &[V::Elem], f: impl Fn(V, V) -> V) {
+        let len = x.len().min(y.len());
+        let full = len - len % V::WIDTH;
+        for i in (0..full).step_by(V::WIDTH) {
+            let a = V::load(x.as_ptr().add(i));
+            let b = V::load(y.as_ptr().add(i));
+            f(a, b).store(x.as_mut_ptr().add(i));
+        }
+        if full < len {
+            let mut a = [V::Elem::ZERO; MAX_WIDTH];
+            let mut b = [V::Elem::ZERO; MAX_WIDTH];
+            a[..len - full].copy_from_slice(&x[full..len]);
+            b[..len - full].copy_from_slice(&y[full..len]);
+            f(V::load(a.as_ptr()), V::load(b.as_ptr())).store(a.as_mut_ptr());
+            x[full..len].copy_from_slice(&a[..len - full]);
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn map<V: Lanes>(x: &mut [V::Elem], f: impl Fn(V) -> V) {
+        let len = x.len();
+        let full = len - len % V::WIDTH;
+        for i in (0..full).step_by(V::WIDTH) {
+            f(V::load(x.as_ptr().add(i))).store(x.as_mut_ptr().add(i));
+        }
+        if full < len {
+            let mut a = [V::Elem::ZERO; MAX_WIDTH];
+            a[..len - full].copy_from_slice(&x[full..]);
+            f(V::load(a.as_ptr())).store(a.as_mut_ptr());
+            x[full..].copy_from_slice(&a[..len - full]);
+        }
+    }
+
+    // exp(x) = 2^n * exp(r) with n = round(x / ln 2) and |r| <= ln 2 / 2, where
+    // exp(r) is a Taylor polynomial evaluated by Horner's rule.
+    #[inline(always)]
+    unsafe fn exp<V: Lanes>(x: V) -> V {
+        let (min, max) = (splat::<V>(V::Elem::EXP_MIN), splat::<V>(V::Elem::EXP_MAX));
+        let clamped = max.min(min.max(x));
+        let n = clamped.mul(splat(std::f64::consts::LOG2_E)).round();
+        let r = n.mul_add(splat(-V::Elem::LN2_HI), clamped);
+        let r = n.mul_add(splat(-V::Elem::LN2_LO), r);
+
+        let degree = V::Elem::EXP_DEGREE;
+        let mut p = splat::<V>(INV_FACTORIALS[degree]);
+        for k in (0..degree).rev() {
+            p = p.mul_add(r, splat(INV_FACTORIALS[k]));
+        }
+        let result = p.mul(n.add(splat(V::Elem::EXPONENT_MAGIC)).pow2());
+
+        // NaN fails both comparisons and stays NaN
+        let result = x.select_gt(max, splat(f64::INFINITY), result);
+        min.select_gt(x, splat(0.0), result)
+    }
+
+    // Each row of the block is held in NR / WIDTH registers and updated with
+    // one FMA per register and step of k. Dispatch never picks registers that
+    // don't split a row evenly, but the scalar loop keeps that case correct.
+    #[inline(always)]
+    pub unsafe fn outer_products<V: Lanes, const MR: usize, const NR: usize>(a: &[V::Elem], b: &[V::Elem], acc: &mut [[V::Elem; NR]; MR]) {
+        if !NR.is_multiple_of(V::WIDTH) {
+            return scalar_outer_products(a, b, acc);
+        }
+        let registers = NR / V::WIDTH;
+        let zero = V::splat(V::Elem::ZERO);
+        let mut sums = [[zero; NR]; MR];
+        for (row, acc_row) in sums.iter_mut().zip(acc.iter()) {
+            for (r, sum) in row.iter_mut().enumerate().take(registers) {
+                *sum = V::load(acc_row.as_ptr().add(r * V::WIDTH));
+            }
+        }
+        for (a_col, b_row) in a.chunks_exact(MR).zip(b.chunks_exact(NR)) {
+            let mut b_regs = [zero; NR];
+            for (r, b_reg) in b_regs.iter_mut().enumerate().take(registers) {
+                *b_reg = V::load(b_row.as_ptr().add(r * V::WIDTH));
+            }
+            for (row, &a_i) in sums.iter_mut().zip(a_col) {
+                let a_i = V::splat(a_i);
+                for (sum, &b_reg) in row.iter_mut().zip(&b_regs).take(registers) {
+                    *sum = a_i.mul_add(b_reg, *sum);
+                }
+            }
+        }
+        for (row, acc_row) in sums.iter().zip(acc.iter_mut()) {
+            for (r, sum) in row.iter().enumerate().take(registers) {
+                sum.store(acc_row.as_mut_ptr().add(r * V::WIDTH));
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub unsafe fn binary<V: Lanes>(op: Binary, x: &mut [V::Elem], y: &[V::Elem]) {
+        match op {
+            Binary::Add => zip::<V>(x, y, #[inline(always)] |a, b| a.add(b)),
+            Binary::Sub => zip::<V>(x, y, #[inline(always)] |a, b| a.sub(b)),
+            Binary::Mul => zip::<V>(x, y, #[inline(always)] |a, b| a.mul(b)),
+        }
+    }
+
+    #[inline(always)]
+    pub unsafe fn unary<V: Lanes>(op: Unary<V::Elem>, x: &mut [V::Elem]) {
+        let one = splat::<V>(1.0);
+        match op {
+            Unary::Scale(s) => {
+                let s = V::splat(s);
+                map::<V>(x, #[inline(always)] |a| a.mul(s))
+            }
+            Unary::Exp => map::<V>(x, #[inline(always)] |a| exp(a)),
+            Unary::Sigmoid => map::<V>(x, #[inline(always)] |a| one.div(one.add(exp(splat::<V>(0.0).sub(a))))),
+            // 1 - 2 / (e^2x + 1), which saturates cleanly to +-1
+            Unary::Tanh => map::<V>(x, #[inline(always)] |a| {
+                let two = splat::<V>(2.0);
+                one.sub(two.div(exp(a.mul(two)).add(one)))
+            }),
+        }
+    }
+}
+
+// Entry points compiled with the instruction set enabled, so the generic
+// kernels and the intrinsics they call inline into vector code.
+macro_rules! entry_points {
+    ($module:ident, $lanes:ident, $features:literal) => {
+        mod $module {
+            use super::*;
+
+            #[target_feature(enable = $features)]
+            pub unsafe fn binary<T: Float>(op: Binary, x: &mut [T], y: &[T]) {
+                kernel::binary::<T::$lanes>(op, x, y)
+            }
+
+            #[target_feature(enable = $features)]
+            pub unsafe fn unary<T: Float>(op: Unary<T>, x: &mut [T]) {
+                kernel::unary::<T::$lanes>(op, x)
+            }
+
+            #[target_feature(enable = $features)]
+            pub unsafe fn outer_products<T: Float, const MR: usize, const NR: usize>(a: &[T], b: &[T], acc: &mut [[T; NR]; MR]) {
+                kernel::outer_products::<T::$lanes, MR, NR>(a, b, acc)
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+entry_points!(avx2, Avx2, "avx2,fma");
+#[cfg(target_arch = "x86_64")]
+entry_points!(avx512, Avx512, "avx512f");
+#[cfg(target_arch = "aarch64")]
+entry_points!(neon, Neon, "neon");
+
+// Implements `Lanes` from intrinsic names. `round`, `pow2` and `select_gt`
+// differ too much between instruction sets and are passed as expressions.
+macro_rules! impl_lanes {
+    (
+        $name:ident, $features:literal, $elem:ty, $register:ty, $width:literal,
+        splat: $splat:ident, load: $load:ident, store: $store:ident,
+        add: $add:ident, sub: $sub:ident, mul: $mul:ident, div: $div:ident,
+        max: $max:ident, min: $min:ident,
+        mul_add: |$fa:ident, $fb:ident, $fc:ident| $mul_add:expr,
+        round: |$r:ident| $round:expr,
+        pow2: |$p:ident| $pow2:expr,
+        select_gt: |$sx:ident, $st:ident, $sa:ident, $sb:ident| $select:expr $(,)?
+    ) => {
+        #[derive(Clone, Copy)]
+        pub struct $name($register);
+
+        impl Lanes for $name {
+            type Elem = $elem;
+            const WIDTH: usize = $width;
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn splat(value: $elem) -> Self {
+                $name($splat(value))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn load(ptr: *const $elem) -> Self {
+                $name($load(ptr))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn store(self, ptr: *mut $elem) {
+                $store(ptr, self.0)
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn add(self, other: Self) -> Self {
+                $name($add(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn sub(self, other: Self) -> Self {
+                $name($sub(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn mul(self, other: Self) -> Self {
+                $name($mul(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn div(self, other: Self) -> Self {
+                $name($div(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn mul_add(self, b: Self, c: Self) -> Self {
+                let ($fa, $fb, $fc) = (self.0, b.0, c.0);
+                $name($mul_add)
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn max(self, other: Self) -> Self {
+                $name($max(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn min(self, other: Self) -> Self {
+                $name($min(self.0, other.0))
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn round(self) -> Self {
+                let $r = self.0;
+                $name($round)
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn pow2(self) -> Self {
+                let $p = self.0;
+                $name($pow2)
+            }
+
+            #[inline]
+            #[target_feature(enable = $features)]
+            unsafe fn select_gt(self, threshold: Self, if_true: Self, if_false: Self) -> Self {
+                let ($sx, $st, $sa, $sb) = (self.0, threshold.0, if_true.0, if_false.0);
+                $name($select)
+            }
+        }
+    };
+}
+
+// `pow2` shifts the biased exponent left by `EXPONENT_MAGIC` into place.
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::Lanes;
+    use std::arch::x86_64::*;
+
+    const ROUND: i32 = _MM_FROUND_TO_NEAREST_INT | _MM_FROUND_NO_EXC;
+
+    impl_lanes!(
+        Avx2F64, "avx2,fma", f64, __m256d, 4,
+        splat: _mm256_set1_pd, load: _mm256_loadu_pd, store: _mm256_storeu_pd,
+        add: _mm256_add_pd, sub: _mm256_sub_pd, mul: _mm256_mul_pd, div: _mm256_div_pd,
+        max: _mm256_max_pd, min: _mm256_min_pd,
+        mul_add: |a, b, c| _mm256_fmadd_pd(a, b, c),
+        round: |v| _mm256_round_pd::<ROUND>(v),
+        pow2: |v| _mm256_castsi256_pd(_mm256_slli_epi64::<52>(_mm256_castpd_si256(v))),
+        select_gt: |x, t, a, b| _mm256_blendv_pd(b, a, _mm256_cmp_pd::<_CMP_GT_OQ>(x, t)),
+    );
+
+    impl_lanes!(
+        Avx2F32, "avx2,fma", f32, __m256, 8,
+        splat: _mm256_set1_ps, load: _mm256_loadu_ps, store: _mm256_storeu_ps,
+        add: _mm256_add_ps, sub: _mm256_sub_ps, mul: _mm256_mul_ps, div: _mm256_div_ps,
+        max: _mm256_max_ps, min: _mm256_min_ps,
+        mul_add: |a, b, c| _mm256_fmadd_ps(a, b, c),
+        round: |v| _mm256_round_ps::<ROUND>(v),
+        pow2: |v| _mm256_castsi256_ps(_mm256_slli_epi32::<23>(_mm256_castps_si256(v))),
+        select_gt: |x, t, a, b| _mm256_blendv_ps(b, a, _mm256_cmp_ps::<_CMP_GT_OQ>(x, t)),
+    );
+
+    impl_lanes!(
+        Avx512F64, "avx512f", f64, __m512d, 8,
+        splat: _mm512_set1_pd, load: _mm512_loadu_pd, store: _mm512_storeu_pd,
+        add: _mm512_add_pd, sub: _mm512_sub_pd, mul: _mm512_mul_pd, div: _mm512_div_pd,
+        max: _mm512_max_pd, min: _mm512_min_pd,
+        mul_add: |a, b, c| _mm512_fmadd_pd(a, b, c),
+        round: |v| _mm512_roundscale_pd::<ROUND>(v),
+        pow2: |v| _mm512_castsi512_pd(_mm512_slli_epi64::<52>(_mm512_castpd_si512(v))),
+        select_gt: |x, t, a, b| _mm512_mask_blend_pd(_mm512_cmp_pd_mask::<_CMP_GT_OQ>(x, t), b, a),
+    );
+
+    impl_lanes!(
+        Avx512F32, "avx512f", f32, __m512, 16,
+        splat: _mm512_set1_ps, load: _mm512_loadu_ps, store: _mm512_storeu_ps,
+        add: _mm512_add_ps, sub: _mm512_sub_ps, mul: _mm512_mul_ps, div: _mm512_div_ps,
+        max: _mm512_max_ps, min: _mm512_min_ps,
+        mul_add: |a, b, c| _mm512_fmadd_ps(a, b, c),
+        round: |v| _mm512_roundscale_ps::<ROUND>(v),
+        pow2: |v| _mm512_castsi512_ps(_mm512_slli_epi32::<23>(_mm512_castps_si512(v))),
+        select_gt: |x, t, a, b| _mm512_mask_blend_ps(_mm512_cmp_ps_mask::<_CMP_GT_OQ>(x, t), b, a),
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arm {
+    use super::Lanes;
+    use std::arch::aarch64::*;
+
+    // NEON max/min propagate NaN from either operand, which covers the
+    // `Lanes` contract as well.
+    impl_lanes!(
+        NeonF64, "neon", f64, float64x2_t, 2,
+        splat: vdupq_n_f64, load: vld1q_f64, store: vst1q_f64,
+        add: vaddq_f64, sub: vsubq_f64, mul: vmulq_f64, div: vdivq_f64,
+        max: vmaxq_f64, min: vminq_f64,
+        mul_add: |a, b, c| vfmaq_f64(c, a, b),
+        round: |v| vrndnq_f64(v),
+        pow2: |v| vreinterpretq_f64_s64(vshlq_n_s64::<52>(vreinterpretq_s64_f64(v))),
+        select_gt: |x, t, a, b| vbslq_f64(vcgtq_f64(x, t), a, b),
+    );
+
+    impl_lanes!(
+        NeonF32, "neon", f32, float32x4_t, 4,
+        splat: vdupq_n_f32, load: vld1q_f32, store: vst1q_f32,
+        add: vaddq_f32, sub: vsubq_f32, mul: vmulq_f32, div: vdivq_f32,
+        max: vmaxq_f32, min: vminq_f32,
+        mul_add: |a, b, c| vfmaq_f32(c, a, b),
+        round: |v| vrndnq_f32(v),
+        pow2: |v| vreinterpretq_f32_s32(vshlq_n_s32::<23>(vreinterpretq_s32_f32(v))),
+        select_gt: |x, t, a, b| vbslq_f32(vcgtq_f32(x, t), a, b),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The levels this CPU can run, which is what makes the `_at` calls sound
+    fn levels() -> Vec<Level> {
+        COMPILED_LEVELS.iter().copied().filter(|&level| supported(level)).collect()
+    }
+
+    #[test]
+    fn test_detected_level_is_supported() {
+        assert!(supported(detected()));
+        assert_eq!(levels()[0], detected());
+        assert_eq!(levels().last(), Some(&Level::Scalar));
+    }
+
+    // Lengths that leave every possible tail for every register width
+    fn inputs() -> Vec<f64> {
+        (0..37).map(|i| (i as f64 - 18.0) * 0.83).collect()
+    }
+
+    #[test]
+    fn test_binary_matches_scalar() {
+        let x = inputs();
+        let y: Vec<f64> = x.iter().map(|v| v * 0.5 - 1.0).collect();
+        for level in levels() {
+            for op in [Binary::Add, Binary::Sub, Binary::Mul] {
+                for len in [0, 1, 3, 7, 8, 17, 37] {
+                    let mut expected = x[..len].to_vec();
+                    scalar_binary(op, &mut expected, &y[..len]);
+                    let mut actual = x[..len].to_vec();
+                    unsafe { binary_at(level, op, &mut actual, &y[..len]); }
+                    assert_eq!(actual, expected, "{:?} {:?}", level, op);
+
+                    let mut actual32: Vec<f32> = x[..len].iter().map(|&v| v as f32).collect();
+                    let y32: Vec<f32> = y[..len].iter().map(|&v| v as f32).collect();
+                    let mut expected32 = actual32.clone();
+                    scalar_binary(op, &mut expected32, &y32);
+                    unsafe { binary_at(level, op, &mut actual32, &y32); }
+                    assert_eq!(actual32, expected32, "{:?} {:?}", level, op);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_outer_products_match_scalar() {
+        // 4 x 8 is the `gemm` block, for both element types; 3 x 4 leaves
+        // registers wider than a row
+        let (a, b) = (inputs(), inputs().iter().rev().map(|v| v * 0.3).collect::<Vec<f64>>());
+        for level in levels() {
+            let mut expected = [[1.0; 8]; 4];
+            scalar_outer_products(&a[..16], &b[..32], &mut expected);
+            let mut actual = [[1.0; 8]; 4];
+            unsafe { outer_products_at(level, &a[..16], &b[..32], &mut actual); }
+            for (x, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+                assert!((x - e).abs() < 1e-12 * (1.0 + e.abs()), "{:?} {} vs {}", level, x, e);
+            }
+
+            let (a32, b32): (Vec<f32>, Vec<f32>) = (a.iter().map(|&v| v as f32).collect(), b.iter().map(|&v| v as f32).collect());
+            let mut expected = [[0.5f32; 4]; 3];
+            scalar_outer_products(&a32[..27], &b32[..36], &mut expected);
+            let mut actual = [[0.5f32; 4]; 3];
+            unsafe { outer_products_at(level, &a32[..27], &b32[..36], &mut actual); }
+            for (x, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+                assert!((x - e).abs() < 1e-4 * (1.0 + e.abs()), "{:?} {} vs {}", level, x, e);
+            }
+
+            let mut expected = [[0.5f32; 8]; 4];
+            scalar_outer_products(&a32[..16], &b32[..32], &mut expected);
+            let mut actual = [[0.5f32; 8]; 4];
+            unsafe { outer_products_at(level, &a32[..16], &b32[..32], &mut actual); }
+            for (x, e) in actual.iter().flatten().zip(expected.iter().flatten()) {
+                assert!((x - e).abs() < 1e-4 * (1.0 + e.abs()), "{:?} {} vs {}", level, x, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_outer_products_use_vector_kernels() {
+        // Every SIMD level runs an explicit kernel for both element types
+        for level in levels().into_iter().filter(|&level| level != Level::Scalar) {
+            for kernel in [outer_products_level::<f64, 8>(level), outer_products_level::<f32, 8>(level)] {
+                assert_ne!(kernel, Level::Scalar, "{:?}", level);
+            }
+        }
+        #[cfg(target_arch = "x86_64")]
+        if supported(Level::Avx512) {
+            assert_eq!(outer_products_level::<f64, 8>(Level::Avx512), Level::Avx512);
+            assert_eq!(outer_products_level::<f32, 8>(Level::Avx512), Level::Avx2);
+            assert_eq!(outer_products_level::<f32, 16>(Level::Avx512), Level::Avx512);
+        }
+    }
+
+    #[test]
+    fn test_approximations_f64() {
+        // Spans the saturated ranges of sigmoid and tanh and the exp cutoffs
+        let mut x = inputs();
+        x.extend([-800.0, -708.5, -30.0, -1e-9, 0.0, 1e-9, 0.5, 30.0, 708.9, 800.0]);
+        for level in levels() {
+            let mut exp = x.clone();
+            unsafe { unary_at(level, Unary::Exp, &mut exp); }
+            for (&v, &e) in x.iter().zip(&exp).filter(|(v, _)| (-708.0..=709.0).contains(*v)) {
+                assert!((e - v.exp()).abs() <= 1e-14 * v.exp(), "{:?} exp({}) = {}", level, v, e);
+            }
+            assert_eq!(exp[x.len() - 1], f64::INFINITY);
+            assert_eq!(exp[x.len() - 10], 0.0);
+
+            for (op, reference) in [(Unary::Sigmoid, (|v: f64| 1.0 / (1.0 + (-v).exp())) as fn(f64) -> f64), (Unary::Tanh, f64::tanh)] {
+                let mut actual = x.clone();
+                unsafe { unary_at(level, op, &mut actual); }
+                for (&v, &a) in x.iter().zip(&actual) {
+                    assert!((a - reference(v)).abs() < 1e-14, "{:?} {:?}({}) = {}", level, op, v, a);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_approximations_f32() {
+        let x: Vec<f32> = inputs().iter().map(|&v| v as f32 * 0.5).collect();
+        for level in levels() {
+            let mut exp = x.clone();
+            unsafe { unary_at(level, Unary::Exp, &mut exp); }
+            for (&v, &e) in x.iter().zip(&exp) {
+                assert!((e - v.exp()).abs() <= 1e-6 * v.exp(), "{:?} exp({}) = {}", level, v, e);
+            }
+
+            let mut sigmoid = x.clone();
+            unsafe { unary_at(level, Unary::Sigmoid, &mut sigmoid); }
+            let mut tanh = x.clone();
+            unsafe { unary_at(level, Unary::Tanh, &mut tanh); }
+            for ((&v, &s), &t) in x.iter().zip(&sigmoid).zip(&tanh) {
+                assert!((s - 1.0 / (1.0 + (-v).exp())).abs() < 1e-6);
+                assert!((t - v.tanh()).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nan_propagates() {
+        for level in levels() {
+            for op in [Unary::Exp, Unary::Sigmoid, Unary::Tanh] {
+                let mut x = vec![0.0, f64::NAN, 1.0];
+                unsafe { unary_at(level, op, &mut x); }
+                assert!(x[1].is_nan() && x[0].is_finite() && x[2].is_finite(), "{:?} {:?}", level, op);
+            }
+        }
+    }
+}